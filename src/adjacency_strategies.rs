@@ -0,0 +1,415 @@
+use std::collections::HashSet;
+
+use log::trace;
+
+use crate::{
+    chess_strategies::CheckedAdd,
+    grid::{parse_cells, Cell, Grid},
+};
+
+/// Number of search nodes after which a marker chain is left alone rather than stall the solver
+const SEARCH_LIMIT: usize = 200_000;
+
+/// A rule on the digits (0-indexed) of two orthogonally adjacent cells
+type Relation = fn(usize, usize) -> bool;
+
+/// A marker between two orthogonally adjacent cells, restricting their digits
+struct Edge {
+    cells: [(usize, usize); 2],
+    allows: Relation,
+}
+impl Edge {
+    fn joins(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        self.cells == [a, b] || self.cells == [b, a]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DotKind {
+    /// The two digits are consecutive
+    White,
+    /// One digit is double the other
+    Black,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Dot {
+    pub kind: DotKind,
+    pub cells: [(usize, usize); 2],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XvKind {
+    /// The two digits sum to 10
+    X,
+    /// The two digits sum to 5
+    V,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Xv {
+    pub kind: XvKind,
+    pub cells: [(usize, usize); 2],
+}
+
+fn consecutive(a: usize, b: usize) -> bool {
+    a.abs_diff(b) == 1
+}
+fn ratio(a: usize, b: usize) -> bool {
+    (a + 1) * 2 == b + 1 || (b + 1) * 2 == a + 1
+}
+fn kropki_pair(a: usize, b: usize) -> bool {
+    consecutive(a, b) || ratio(a, b)
+}
+fn sum_ten(a: usize, b: usize) -> bool {
+    a + b + 2 == 10
+}
+fn sum_five(a: usize, b: usize) -> bool {
+    a + b + 2 == 5
+}
+fn xv_pair(a: usize, b: usize) -> bool {
+    sum_ten(a, b) || sum_five(a, b)
+}
+
+/// Parses two orthogonally adjacent cells, e.g. `r1c1,r1c2`
+pub fn parse_pair(s: &str) -> Result<[(usize, usize); 2], String> {
+    let cells = parse_cells(s)?;
+    let [a, b] = cells[..] else {
+        return Err(format!("expected exactly two cells, found `{}`", s));
+    };
+    if a.0.abs_diff(b.0) + a.1.abs_diff(b.1) != 1 {
+        return Err(format!("cells in `{}` are not orthogonally adjacent", s));
+    }
+    Ok([a, b])
+}
+
+/// Removes digits that `forbidden` rules out next to solved cells, for every orthogonally
+/// adjacent pair without a marker
+fn unmarked_neighbours(grid: &mut Grid, edges: &[Edge], forbidden: Relation) -> bool {
+    let mut result = false;
+    for r in 0..9 {
+        for c in 0..9 {
+            if let Cell::Solved(n) = grid[r][c] {
+                for (rr, cc) in [
+                    (r.checked_sub(1), Some(c)),
+                    (r.sudoku_add(1), Some(c)),
+                    (Some(r), c.checked_sub(1)),
+                    (Some(r), c.sudoku_add(1)),
+                ] {
+                    let (Some(rr), Some(cc)) = (rr, cc) else {
+                        continue;
+                    };
+                    if edges.iter().any(|e| e.joins((r, c), (rr, cc))) {
+                        continue;
+                    }
+                    let allowed = std::array::from_fn(|m| !forbidden(n, m));
+                    result |= grid[rr][cc].restrict(&allowed);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// How the digits of two cells in a chain constrain each other
+#[derive(Clone, Copy)]
+struct Link {
+    distinct: bool,
+    allows: Option<Relation>,
+    forbidden: Option<Relation>,
+}
+impl Link {
+    fn fits(&self, a: usize, b: usize) -> bool {
+        !(self.distinct && a == b)
+            && self.allows.is_none_or(|allows| allows(a, b))
+            && self.forbidden.is_none_or(|forbidden| !forbidden(a, b))
+    }
+}
+
+struct ChainSearch {
+    options: Vec<Vec<usize>>,
+    links: Vec<Vec<Link>>,
+    chosen: Vec<usize>,
+    nodes: usize,
+    supported: Vec<[bool; 9]>,
+}
+impl ChainSearch {
+    /// Returns false if the search was abandoned at `SEARCH_LIMIT` nodes
+    fn run(&mut self, depth: usize) -> bool {
+        self.nodes += 1;
+        if self.nodes > SEARCH_LIMIT {
+            return false;
+        }
+        if depth == self.options.len() {
+            for (i, n) in self.chosen.iter().enumerate() {
+                self.supported[i][*n] = true;
+            }
+            return true;
+        }
+        for n in self.options[depth].clone() {
+            let links = &self.links[depth];
+            if self
+                .chosen
+                .iter()
+                .zip(links)
+                .all(|(m, link)| link.fits(*m, n))
+            {
+                self.chosen.push(n);
+                let finished = self.run(depth + 1);
+                self.chosen.pop();
+                if !finished {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Enumerates every fill of each connected chain of markers, keeping only the digits that
+/// satisfy all markers along the chain.
+/// If `forbidden` is given, unmarked adjacent pairs within a chain must not satisfy it.
+/// Chains too loose to search within `SEARCH_LIMIT` nodes are skipped
+fn marker_chains(grid: &mut Grid, edges: &[Edge], forbidden: Option<Relation>) -> bool {
+    let mut result = false;
+    let mut seen = HashSet::new();
+    for start in edges.iter().map(|e| e.cells[0]) {
+        if !seen.insert(start) {
+            continue;
+        }
+        // Breadth-first order, so each cell is constrained by an earlier one in the search
+        let mut chain = vec![start];
+        let mut i = 0;
+        while i < chain.len() {
+            let cell = chain[i];
+            for e in edges.iter().filter(|e| e.cells.contains(&cell)) {
+                let other = if e.cells[0] == cell {
+                    e.cells[1]
+                } else {
+                    e.cells[0]
+                };
+                if seen.insert(other) {
+                    chain.push(other);
+                }
+            }
+            i += 1;
+        }
+
+        let links = chain
+            .iter()
+            .map(|a| {
+                chain
+                    .iter()
+                    .map(|b| {
+                        let adjacent = a.0.abs_diff(b.0) + a.1.abs_diff(b.1) == 1;
+                        let allows = edges.iter().find(|e| e.joins(*a, *b)).map(|e| e.allows);
+                        Link {
+                            distinct: grid.sees(*a, *b),
+                            allows,
+                            forbidden: forbidden.filter(|_| adjacent && allows.is_none()),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut search = ChainSearch {
+            options: chain.iter().map(|(r, c)| grid[*r][*c].options()).collect(),
+            links,
+            chosen: Vec::new(),
+            nodes: 0,
+            supported: vec![[false; 9]; chain.len()],
+        };
+        if !search.run(0) {
+            trace!("Gave up on the marker chain from {:?}", start);
+            continue;
+        }
+        for ((r, c), allowed) in chain.iter().zip(search.supported.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+pub fn kropki(grid: &mut Grid, dots: &[Dot], negative: bool) -> bool {
+    trace!("Searching for kropki dots");
+    let edges = dots
+        .iter()
+        .map(|dot| Edge {
+            cells: dot.cells,
+            allows: match dot.kind {
+                DotKind::White => consecutive,
+                DotKind::Black => ratio,
+            },
+        })
+        .collect::<Vec<_>>();
+    let forbidden = if negative {
+        Some(kropki_pair as Relation)
+    } else {
+        None
+    };
+
+    let mut result = marker_chains(grid, &edges, forbidden);
+    if negative {
+        result |= unmarked_neighbours(grid, &edges, kropki_pair);
+    }
+    result
+}
+
+pub fn xv(grid: &mut Grid, markers: &[Xv], negative: bool) -> bool {
+    trace!("Searching for XV pairs");
+    let edges = markers
+        .iter()
+        .map(|marker| Edge {
+            cells: marker.cells,
+            allows: match marker.kind {
+                XvKind::X => sum_ten,
+                XvKind::V => sum_five,
+            },
+        })
+        .collect::<Vec<_>>();
+    let forbidden = if negative {
+        Some(xv_pair as Relation)
+    } else {
+        None
+    };
+
+    let mut result = marker_chains(grid, &edges, forbidden);
+    if negative {
+        result |= unmarked_neighbours(grid, &edges, xv_pair);
+    }
+    result
+}
+
+pub fn nonconsecutive(grid: &mut Grid) -> bool {
+    trace!("Searching for consecutive neighbours");
+    unmarked_neighbours(grid, &[], consecutive)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        adjacency_strategies::{
+            kropki, nonconsecutive, xv, ChainSearch, Dot, DotKind, Link, Xv, XvKind, SEARCH_LIMIT,
+        },
+        grid::{Cell, Grid},
+    };
+
+    #[test]
+    fn test_kropki() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Solved(4);
+        grid[8][8] = Cell::Unsolved([true, true, true, false, false, false, false, false, false]);
+        let dots = [
+            Dot {
+                kind: DotKind::White,
+                cells: [(0, 0), (0, 1)],
+            },
+            Dot {
+                kind: DotKind::Black,
+                cells: [(8, 8), (8, 7)],
+            },
+        ];
+
+        assert!(kropki(&mut grid, &dots, false));
+
+        assert_eq!(grid[0][1].candidates(), vec![3, 5]);
+        assert_eq!(grid[8][7].candidates(), vec![0, 1, 3, 5]);
+        assert_eq!(grid[1][0], Cell::Unsolved([true; 9]));
+    }
+
+    #[test]
+    fn test_kropki_chain() {
+        let mut grid = Grid::default();
+        // Three cells in a row joined by black dots must hold 1-2-4 or 2-4-8, either way round
+        let dots = [
+            Dot {
+                kind: DotKind::Black,
+                cells: [(0, 0), (0, 1)],
+            },
+            Dot {
+                kind: DotKind::Black,
+                cells: [(0, 1), (0, 2)],
+            },
+        ];
+
+        assert!(kropki(&mut grid, &dots, false));
+
+        assert_eq!(grid[0][0].candidates(), vec![0, 1, 3, 7]);
+        assert_eq!(grid[0][1].candidates(), vec![1, 3]);
+        assert_eq!(grid[0][2].candidates(), vec![0, 1, 3, 7]);
+    }
+
+    #[test]
+    fn test_chain_search_limit() {
+        // Twenty cells with no rule between them have 9^20 fills, far past the limit
+        let loose = Link {
+            distinct: false,
+            allows: None,
+            forbidden: None,
+        };
+        let mut search = ChainSearch {
+            options: vec![(0..9).collect(); 20],
+            links: vec![vec![loose; 20]; 20],
+            chosen: Vec::new(),
+            nodes: 0,
+            supported: vec![[false; 9]; 20],
+        };
+
+        assert!(!search.run(0));
+
+        assert_eq!(search.nodes, SEARCH_LIMIT + 1);
+    }
+
+    #[test]
+    fn test_kropki_negative() {
+        let mut grid = Grid::default();
+        grid[4][4] = Cell::Solved(3);
+
+        assert!(kropki(&mut grid, &[], true));
+
+        for (r, c) in [(3, 4), (5, 4), (4, 3), (4, 5)] {
+            assert_eq!(grid[r][c].candidates(), vec![0, 3, 5, 6, 8]);
+        }
+        assert_eq!(grid[3][3], Cell::Unsolved([true; 9]));
+    }
+
+    #[test]
+    fn test_xv() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Solved(2);
+        let markers = [
+            Xv {
+                kind: XvKind::X,
+                cells: [(0, 0), (0, 1)],
+            },
+            Xv {
+                kind: XvKind::V,
+                cells: [(8, 8), (7, 8)],
+            },
+        ];
+
+        assert!(xv(&mut grid, &markers, true));
+
+        assert_eq!(grid[0][1].candidates(), vec![6]);
+        assert_eq!(grid[8][8].candidates(), vec![0, 1, 2, 3]);
+        assert_eq!(grid[7][8].candidates(), vec![0, 1, 2, 3]);
+        // 3 is unmarked next to r2c1, so it cannot sum to 5 or 10
+        assert_eq!(grid[1][0].candidates(), vec![0, 2, 3, 4, 5, 7, 8]);
+    }
+
+    #[test]
+    fn test_nonconsecutive() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Solved(0);
+        grid[4][4] = Cell::Solved(4);
+
+        assert!(nonconsecutive(&mut grid));
+
+        assert_eq!(grid[0][1].candidates(), vec![0, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(grid[1][0].candidates(), vec![0, 2, 3, 4, 5, 6, 7, 8]);
+        for (r, c) in [(3, 4), (5, 4), (4, 3), (4, 5)] {
+            assert_eq!(grid[r][c].candidates(), vec![0, 1, 2, 4, 6, 7, 8]);
+        }
+        assert_eq!(grid[3][3], Cell::Unsolved([true; 9]));
+    }
+}
@@ -0,0 +1,129 @@
+use std::{collections::BTreeSet, str::FromStr};
+
+use itertools::Itertools;
+use log::trace;
+
+use crate::{
+    grid::{parse_cells, Grid},
+    sums::sum_options,
+};
+
+/// A circle (or multi-cell pill) whose value equals the sum of the digits along its arrow.
+/// Pill cells are read in order as the digits of a single number
+#[derive(Clone, Debug)]
+pub struct Arrow {
+    pub circle: Vec<(usize, usize)>,
+    pub shaft: Vec<(usize, usize)>,
+}
+impl FromStr for Arrow {
+    type Err = String;
+
+    /// Parses `CIRCLE=SHAFT`, each a comma-separated list of cells, e.g. `r1c1,r1c2=r2c3,r3c4`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((circle, shaft)) = s.split_once('=') else {
+            return Err(format!("invalid arrow `{}`, expected CIRCLE=SHAFT", s));
+        };
+        let arrow = Arrow {
+            circle: parse_cells(circle)?,
+            shaft: parse_cells(shaft)?,
+        };
+        if arrow.circle.len() > 2 {
+            return Err(format!("arrow pill `{}` is longer than 2 cells", circle));
+        }
+        Ok(arrow)
+    }
+}
+
+/// Every way of filling the circle from its current options, paired with the value it spells
+fn circle_fills(grid: &Grid, circle: &[(usize, usize)]) -> Vec<(Vec<usize>, usize)> {
+    circle
+        .iter()
+        .map(|(r, c)| grid[*r][*c].options())
+        .multi_cartesian_product()
+        .filter(|fill| {
+            circle
+                .iter()
+                .enumerate()
+                .tuple_combinations()
+                .all(|((i, a), (j, b))| !grid.sees(*a, *b) || fill[i] != fill[j])
+        })
+        .map(|fill| {
+            let value = fill.iter().fold(0, |acc, n| acc * 10 + n + 1);
+            (fill, value)
+        })
+        .collect()
+}
+
+fn process_arrow(grid: &mut Grid, arrow: &Arrow) -> bool {
+    let fills = circle_fills(grid, &arrow.circle);
+    let targets = fills
+        .iter()
+        .map(|(_, value)| *value)
+        .collect::<BTreeSet<_>>();
+    let Some(shaft) = sum_options(grid, &arrow.shaft, &targets) else {
+        trace!("Arrow at {:?} is too unconstrained to search", arrow.circle);
+        return false;
+    };
+
+    let mut circle_allowed = vec![[false; 9]; arrow.circle.len()];
+    for (fill, _) in fills.iter().filter(|(_, value)| shaft.sums.contains(value)) {
+        for (allowed, n) in circle_allowed.iter_mut().zip(fill) {
+            allowed[*n] = true;
+        }
+    }
+
+    let mut result = false;
+    for ((r, c), allowed) in arrow.circle.iter().zip(circle_allowed.iter()) {
+        result |= grid[*r][*c].restrict(allowed);
+    }
+    for ((r, c), allowed) in arrow.shaft.iter().zip(shaft.candidates.iter()) {
+        result |= grid[*r][*c].restrict(allowed);
+    }
+    result
+}
+
+pub fn arrows(grid: &mut Grid, arrows: &[Arrow]) -> bool {
+    trace!("Searching for arrow sums");
+    let mut result = false;
+    for arrow in arrows {
+        result |= process_arrow(grid, arrow);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        arrow_strategies::{arrows, Arrow},
+        grid::{Cell, Grid},
+    };
+
+    #[test]
+    fn test_arrows() {
+        let mut grid = Grid::default();
+        let arrow: Arrow = "r1c1=r1c2,r1c3".parse().unwrap();
+
+        assert!(arrows(&mut grid, &[arrow]));
+
+        // Two distinct digits in a row sum to at least 3, and at most 8 with a circle of 9
+        assert_eq!(grid[0][0].candidates(), vec![2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(grid[0][1].candidates(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(grid[0][2].candidates(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_arrow_pill() {
+        let mut grid = Grid::default();
+        for r in 1..4 {
+            grid[r][0] =
+                Cell::Unsolved([false, false, false, false, false, false, true, true, true]);
+        }
+        let arrow: Arrow = "r1c1,r1c2=r2c1,r3c1,r4c1".parse().unwrap();
+
+        assert!(arrows(&mut grid, &[arrow]));
+
+        // Three distinct digits from 7, 8 and 9 always sum to 24
+        assert!(grid[0][0].candidates() == vec![1]);
+        assert!(grid[0][1].candidates() == vec![3]);
+    }
+}
@@ -1,5 +1,3 @@
-use std::collections::{HashMap, HashSet};
-
 use itertools::Itertools;
 
 use log::trace;
@@ -13,7 +11,7 @@ pub fn naked_singles(grid: &mut Grid) -> bool {
     for ref mut cell in grid.iter_mut().flatten() {
         if let Cell::Unsolved(candidates) = cell {
             if candidates.iter().filter(|t| **t).count() == 1 {
-                let n = candidates.iter().position(|c| *c == true).unwrap();
+                let n = candidates.iter().position(|c| *c).unwrap();
                 **cell = Cell::Solved(n);
                 result = true;
             }
@@ -32,7 +30,7 @@ pub fn basic_elimination(grid: &mut Grid) -> bool {
             .iter()
             .filter_map(|c| {
                 if let Cell::Solved(n) = c {
-                    Some(n.clone())
+                    Some(*n)
                 } else {
                     None
                 }
@@ -47,14 +45,8 @@ pub fn basic_elimination(grid: &mut Grid) -> bool {
         }
         result
     }
-    for row in grid.iter_mut() {
-        result |= process_group(row.iter_mut().collect_vec());
-    }
-    for col in grid.cols() {
-        result |= process_group(col);
-    }
-    for bx in grid.boxes() {
-        result |= process_group(bx);
+    for i in 0..grid.house_count() {
+        result |= process_group(grid.house(i));
     }
     result
 }
@@ -65,6 +57,10 @@ pub fn hidden_singles(grid: &mut Grid) -> bool {
     fn process_group(group: &mut Vec<&mut Cell>) -> bool {
         let mut result = false;
         for i in 0..9 {
+            // Candidates may not have been eliminated yet if strategies were reordered
+            if group.iter().any(|c| **c == Cell::Solved(i)) {
+                continue;
+            }
             let cells = group
                 .iter_mut()
                 .filter(|c| {
@@ -75,212 +71,251 @@ pub fn hidden_singles(grid: &mut Grid) -> bool {
                 })
                 .collect_vec();
             if cells.len() == 1 {
-                result = true;
+                let mut newcands = [false; 9];
+                newcands[i] = true;
                 for cell in cells {
-                    let mut newcands = [false; 9];
-                    newcands[i] = true;
-                    **cell = Cell::Unsolved(newcands);
+                    if **cell != Cell::Unsolved(newcands) {
+                        result = true;
+                        **cell = Cell::Unsolved(newcands);
+                    }
                 }
             }
         }
         result
     }
-    for row in grid.iter_mut() {
-        result |= process_group(&mut row.iter_mut().collect_vec());
-    }
-    for mut col in grid.cols() {
-        result |= process_group(&mut col);
-    }
-    for mut bx in grid.boxes() {
-        result |= process_group(&mut bx);
+    for i in 0..grid.house_count() {
+        result |= process_group(&mut grid.house(i));
     }
 
     result
 }
-pub fn naked_tuples(grid: &mut Grid) -> bool {
-    trace!("Searching for naked tuples");
-    let mut result = false;
-
-    fn process_group(group: &mut Vec<&mut Cell>) -> bool {
-        let mut result = false;
-        let mut map: HashMap<Cell, usize> = HashMap::new();
-        for cell in group.iter() {
-            if let Some(count) = map.get_mut(cell) {
-                *count += 1;
-            } else {
-                map.insert(**cell, 1);
-            }
-        }
+/// The cells a tuple or pointing deduction is based on, and what it rules out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    /// The house the cells were found in, as numbered by `Grid::house_cells`
+    pub house: usize,
+    pub cells: Vec<(usize, usize)>,
+    /// The 0-indexed digits the pattern is made of
+    pub digits: Vec<usize>,
+    /// The candidates it removes, as a cell and a 0-indexed digit
+    pub eliminations: Vec<((usize, usize), usize)>,
+}
 
-        for id in map.iter().filter_map(|(c, count)| {
-            if c.candidates().len() == *count {
-                Some(c)
-            } else {
-                None
-            }
-        }) {
-            let candidates = id.candidates();
-            for cell in group.iter_mut().filter(|c| **c != id) {
-                for cand in candidates.iter() {
-                    if cell.remove_candidate(*cand) {
-                        result = true;
-                    };
-                }
-            }
+/// Removes the eliminations of every pattern, all found on the grid as it was before any of
+/// them were applied
+fn apply(grid: &mut Grid, patterns: Vec<Pattern>) -> bool {
+    let mut result = false;
+    for pattern in patterns {
+        trace!(
+            "Digits {:?} in {:?}, house {}",
+            pattern.digits,
+            pattern.cells,
+            pattern.house
+        );
+        for ((r, c), n) in pattern.eliminations {
+            result |= grid[r][c].remove_candidate(n);
         }
-
-        result
-    }
-    for row in grid.0.iter_mut() {
-        result |= process_group(&mut row.iter_mut().collect_vec());
-    }
-    for col in grid.cols().iter_mut() {
-        result |= process_group(col);
-    }
-    for bx in grid.boxes().iter_mut() {
-        result |= process_group(bx);
     }
-
     result
 }
-pub fn hidden_tuples(grid: &mut Grid) -> bool {
-    let mut result = false;
-    trace!("Searching for hidden tuples");
 
-    fn process_group(group: &mut Vec<&mut Cell>) -> bool {
-        let mut result = false;
-        let mut map: HashMap<usize, usize> = HashMap::new();
-        for cell in group.iter() {
-            for cand in cell.candidates() {
-                if let Some(count) = map.get_mut(&cand) {
-                    *count += 1;
-                } else {
-                    map.insert(cand, 1);
-                }
+/// Cells in a house which share the same n candidates between n of them, so those digits can
+/// be removed from the rest of the house
+pub fn naked_tuple_patterns(grid: &Grid) -> Vec<Pattern> {
+    let mut patterns = vec![];
+    for house in 0..grid.house_count() {
+        let cells = grid.house_cells(house);
+        for id in cells.iter().map(|&(r, c)| grid[r][c]).unique() {
+            let digits = id.candidates();
+            let tuple = cells
+                .iter()
+                .copied()
+                .filter(|&(r, c)| grid[r][c] == id)
+                .collect_vec();
+            if digits.len() != tuple.len() {
+                continue;
+            }
+            let eliminations = cells
+                .iter()
+                .filter(|cell| !tuple.contains(cell))
+                .flat_map(|&(r, c)| {
+                    let candidates = grid[r][c].candidates();
+                    digits
+                        .iter()
+                        .filter(move |n| candidates.contains(n))
+                        .map(move |&n| ((r, c), n))
+                })
+                .collect_vec();
+            if !eliminations.is_empty() {
+                patterns.push(Pattern {
+                    house,
+                    cells: tuple,
+                    digits,
+                    eliminations,
+                });
             }
         }
+    }
+    patterns
+}
+pub fn naked_tuples(grid: &mut Grid) -> bool {
+    trace!("Searching for naked tuples");
+    let patterns = naked_tuple_patterns(grid);
+    apply(grid, patterns)
+}
 
+/// n digits which only fit in the same n cells of a house, so those cells can't hold anything
+/// else
+pub fn hidden_tuple_patterns(grid: &Grid) -> Vec<Pattern> {
+    let mut patterns = vec![];
+    for house in 0..grid.house_count() {
+        let cells = grid.house_cells(house);
+        let counts = (0..9)
+            .map(|n| {
+                cells
+                    .iter()
+                    .filter(|(r, c)| grid[*r][*c].candidates().contains(&n))
+                    .count()
+            })
+            .collect_vec();
         for len in 2..=4 {
-            let cands = map
+            let digits = (0..9)
+                .filter(|&n| counts[n] > 0 && counts[n] <= len)
+                .collect_vec();
+            let tuple = cells
                 .iter()
-                .filter_map(|(k, v)| if v <= &len { Some(k) } else { None })
+                .copied()
+                .filter(|(r, c)| grid[*r][*c].candidates().iter().any(|n| digits.contains(n)))
                 .collect_vec();
-            let mut cells = group
-                .iter_mut()
-                .filter(|c| c.candidates().iter().any(|c| cands.contains(&c)))
+            if tuple.len() != len || digits.len() != len {
+                continue;
+            }
+            let eliminations = tuple
+                .iter()
+                .flat_map(|&(r, c)| {
+                    grid[r][c]
+                        .candidates()
+                        .into_iter()
+                        .filter(|n| !digits.contains(n))
+                        .map(move |n| ((r, c), n))
+                })
                 .collect_vec();
-            if cells.len() == len && cells.len() == cands.len() {
-                for cell in cells.iter_mut() {
-                    for cand in cell.candidates() {
-                        if !cands.contains(&&cand) {
-                            cell.remove_candidate(cand);
-                        }
-                    }
-                }
-                result = true;
+            if !eliminations.is_empty() {
+                patterns.push(Pattern {
+                    house,
+                    cells: tuple,
+                    digits,
+                    eliminations,
+                });
             }
         }
-        result
-    }
-    for row in grid.iter_mut() {
-        result |= process_group(&mut row.iter_mut().collect_vec());
-    }
-    for col in grid.cols().iter_mut() {
-        result |= process_group(col);
     }
-    for bx in grid.boxes().iter_mut() {
-        result |= process_group(bx);
-    }
-
-    result
+    patterns
+}
+pub fn hidden_tuples(grid: &mut Grid) -> bool {
+    trace!("Searching for hidden tuples");
+    let patterns = hidden_tuple_patterns(grid);
+    apply(grid, patterns)
 }
-pub fn pointing_tuples(grid: &mut Grid) -> bool {
-    let mut result = false;
 
-    // cand -> (row/col, box in row/col)
-    let mut row_clears: HashMap<usize, HashSet<(usize, usize)>> = HashMap::new();
-    let mut col_clears: HashMap<usize, HashSet<(usize, usize)>> = HashMap::new();
-
-    for (i_bx, bx) in grid.boxes().iter().enumerate() {
-        let box_row = i_bx / 3;
-        let box_col = i_bx % 3;
-        let mut row_map: HashMap<usize, HashSet<usize>> = HashMap::new();
-        let mut col_map: HashMap<usize, HashSet<usize>> = HashMap::new();
-        for (i_cell, cell) in bx.iter().enumerate() {
-            for cand in cell.candidates() {
-                if let Some(vec) = row_map.get_mut(&cand) {
-                    vec.insert(i_cell / 3);
-                } else {
-                    row_map.insert(cand, HashSet::from([i_cell / 3]));
-                }
-                if let Some(vec) = col_map.get_mut(&cand) {
-                    vec.insert(i_cell % 3);
-                } else {
-                    col_map.insert(cand, HashSet::from([i_cell % 3]));
-                }
-            }
+/// A digit confined to where a house meets another, such as the row or column of a box, so it
+/// can't appear elsewhere in that other house.
+/// Any two houses sharing at least two cells are paired, so jigsaw regions and extra houses
+/// point and claim like boxes and lines do
+pub fn pointing_patterns(grid: &Grid) -> Vec<Pattern> {
+    let houses = (0..grid.house_count())
+        .map(|i| grid.house_cells(i))
+        .collect_vec();
+    let mut houses_of = vec![vec![]; 81];
+    for (i, cells) in houses.iter().enumerate() {
+        for (r, c) in cells {
+            houses_of[r * 9 + c].push(i);
         }
-        for (k, row_in_box) in row_map.iter().filter_map(|(k, v)| {
-            if v.len() == 1 {
-                v.iter().last().map(|v| (k, v))
-            } else {
-                None
-            }
-        }) {
-            let overall_row = row_in_box + (box_row * 3);
-            let tuple = (overall_row, box_col);
-            if let Some(set) = row_clears.get_mut(k) {
-                set.insert(tuple);
-            } else {
-                row_clears.insert(*k, HashSet::from([tuple]));
+    }
+    let has =
+        |(r, c): (usize, usize), n: usize| matches!(grid[r][c], Cell::Unsolved(cands) if cands[n]);
+
+    let mut patterns = vec![];
+    for (i, cells) in houses.iter().enumerate() {
+        let mut shared = vec![0; houses.len()];
+        for (r, c) in cells {
+            for j in &houses_of[r * 9 + c] {
+                shared[*j] += 1;
             }
         }
-        for (k, col_in_box) in col_map.iter().filter_map(|(k, v)| {
-            if v.len() == 1 {
-                v.iter().last().map(|v| (k, v))
-            } else {
-                None
-            }
-        }) {
-            let overall_col = col_in_box + (box_col * 3);
-            let tuple = (overall_col, box_row);
-            if let Some(set) = col_clears.get_mut(k) {
-                set.insert(tuple);
-            } else {
-                col_clears.insert(*k, HashSet::from([tuple]));
+        let meeting = (0..houses.len())
+            .filter(|j| *j != i && shared[*j] >= 2)
+            .map(|j| &houses[j])
+            .collect_vec();
+        for n in 0..9 {
+            let spots = cells
+                .iter()
+                .filter(|cell| has(**cell, n))
+                .copied()
+                .collect_vec();
+            if spots.is_empty() {
+                continue;
             }
-        }
-    }
-
-    for (cand, set) in row_clears {
-        for (row, box_col) in set {
-            for (i, cell) in grid.0[row].iter_mut().enumerate() {
-                if i / 3 != box_col {
-                    cell.remove_candidate(cand);
-                    result = true;
+            for other in meeting
+                .iter()
+                .filter(|other| spots.iter().all(|s| other.contains(s)))
+            {
+                let eliminations = other
+                    .iter()
+                    .filter(|cell| !cells.contains(cell) && has(**cell, n))
+                    .map(|cell| (*cell, n))
+                    .collect_vec();
+                if !eliminations.is_empty() {
+                    patterns.push(Pattern {
+                        house: i,
+                        cells: spots.clone(),
+                        digits: vec![n],
+                        eliminations,
+                    });
                 }
             }
         }
     }
-    for (cand, set) in col_clears {
-        for (col, box_row) in set {
-            for (i, cell) in grid.cols().get_mut(col).unwrap().iter_mut().enumerate() {
-                if i / 3 != box_row {
-                    cell.remove_candidate(cand);
-                    result = true;
-                }
-            }
-        }
+    patterns
+}
+pub fn pointing_tuples(grid: &mut Grid) -> bool {
+    trace!("Searching for pointing tuples");
+    let patterns = pointing_patterns(grid);
+    apply(grid, patterns)
+}
+
+/// The patterns a tuple or pointing strategy would apply to `grid` next
+pub fn patterns(strategy: &str, grid: &Grid) -> Vec<Pattern> {
+    match strategy {
+        "naked-tuples" => naked_tuple_patterns(grid),
+        "hidden-tuples" => hidden_tuple_patterns(grid),
+        "pointing-tuples" => pointing_patterns(grid),
+        _ => vec![],
     }
+}
 
-    result
+/// The house in which `n` only fits in `cell`, making it a hidden single
+pub fn hidden_single_house(grid: &Grid, cell: (usize, usize), n: usize) -> Option<usize> {
+    (0..grid.house_count()).find(|&i| {
+        let cells = grid.house_cells(i);
+        cells.contains(&cell)
+            && cells
+                .iter()
+                .filter(|(r, c)| grid[*r][*c].candidates().contains(&n))
+                .count()
+                == 1
+    })
 }
 
 #[cfg(test)]
 mod test {
     use std::collections::HashSet;
 
-    use crate::{basic_sudoku::*, Cell, Grid};
+    use crate::{
+        basic_sudoku::*,
+        grid::{Cell, Grid},
+        houses::diagonals,
+    };
     impl Cell {
         fn exact_candidates(&self, candidates: &HashSet<usize>) -> bool {
             if let Cell::Unsolved(c) = self {
@@ -304,7 +339,7 @@ mod test {
 
     #[test]
     fn test_naked_singles() {
-        let mut grid = Grid([[Cell::default(); 9]; 9]);
+        let mut grid = Grid::default();
         let mut opts = [false; 9];
         opts[0] = true;
         grid[0][0] = Cell::Unsolved(opts);
@@ -314,7 +349,7 @@ mod test {
 
     #[test]
     fn test_basic_elimination() {
-        let mut grid = Grid([[Cell::default(); 9]; 9]);
+        let mut grid = Grid::default();
         grid[0][0] = Cell::Solved(0);
         let reduced = HashSet::from([1, 2, 3, 4, 5, 6, 7, 8]);
         let unreduced = HashSet::from([0, 1, 2, 3, 4, 5, 6, 7, 8]);
@@ -344,18 +379,18 @@ mod test {
 
     #[test]
     fn test_hidden_singles() {
-        let mut grid = Grid([[Cell::default(); 9]; 9]);
+        let mut grid = Grid::default();
 
         for cell in grid[8].iter_mut().skip(1) {
             cell.remove_candidate(0);
         }
 
-        for cell in grid.cols().get_mut(8).unwrap().iter_mut().skip(1) {
-            cell.remove_candidate(1);
+        for (r, c) in grid.house_cells(17).into_iter().skip(1) {
+            grid[r][c].remove_candidate(1);
         }
 
-        for cell in grid.boxes().get_mut(4).unwrap().iter_mut().skip(1) {
-            cell.remove_candidate(2);
+        for (r, c) in grid.house_cells(22).into_iter().skip(1) {
+            grid[r][c].remove_candidate(2);
         }
 
         assert!(hidden_singles(&mut grid));
@@ -363,11 +398,22 @@ mod test {
         assert!(grid[8][0].exact_candidates(&HashSet::from([0])));
         assert!(grid[0][8].exact_candidates(&HashSet::from([1])));
         assert!(grid[3][3].exact_candidates(&HashSet::from([2])));
+
+        // Applying it again finds nothing new
+        assert!(!hidden_singles(&mut grid));
+
+        // A digit already placed in a house isn't a hidden single there
+        let mut grid = Grid::default();
+        for (c, cell) in grid[0].iter_mut().enumerate().skip(1) {
+            *cell = Cell::Solved(c - 1);
+        }
+        assert!(hidden_singles(&mut grid));
+        assert!(grid[0][0].exact_candidates(&HashSet::from([8])));
     }
 
     #[test]
     fn test_naked_tuples() {
-        let mut grid = Grid([[Cell::default(); 9]; 9]);
+        let mut grid = Grid::default();
 
         let a = [true, true, false, false, false, false, false, false, false];
         grid[0][2] = Cell::Unsolved(a);
@@ -389,105 +435,142 @@ mod test {
             .filter_map(|(i, c)| if i == 2 || i == 6 { None } else { Some(c) })
             .all(|c| !c.has_candidate(0) && !c.has_candidate(1)));
         assert!(grid
-            .cols()
-            .get(0)
-            .unwrap()
+            .house_cells(9)
             .iter()
-            .enumerate()
-            .filter_map(|(i, c)| if i == 1 || i == 6 { None } else { Some(c) })
-            .all(|c| !c.has_candidate(2) && !c.has_candidate(3)));
+            .filter(|cell| ![(1, 0), (6, 0)].contains(cell))
+            .all(|&(r, c)| !grid[r][c].has_candidate(2) && !grid[r][c].has_candidate(3)));
         assert!(grid
-            .boxes()
-            .get(0)
-            .unwrap()
+            .house_cells(18)
             .iter()
-            .enumerate()
-            .filter_map(|(i, c)| if i == 0 || i == 4 { None } else { Some(c) })
-            .all(|c| !c.has_candidate(4) && !c.has_candidate(5)));
+            .filter(|cell| ![(0, 0), (1, 1)].contains(cell))
+            .all(|&(r, c)| !grid[r][c].has_candidate(4) && !grid[r][c].has_candidate(5)));
     }
 
     #[test]
     fn test_hidden_tuples() {
-        let mut grid = Grid([[Cell::default(); 9]; 9]);
+        let mut grid = Grid::default();
 
-        let row_refs: [*const Cell; 2] = [&grid.0[0][4], &grid.0[0][7]];
-        for cell in grid.0[0].iter_mut() {
-            if row_refs.contains(&(&(*cell) as *const Cell)) {
-                continue;
+        let row_cells = [(0, 4), (0, 7)];
+        for (r, c) in grid.house_cells(0) {
+            if !row_cells.contains(&(r, c)) {
+                grid[r][c].remove_candidate(0);
+                grid[r][c].remove_candidate(1);
             }
-            cell.remove_candidate(0);
-            cell.remove_candidate(1);
         }
 
-        let col_refs: [*mut Cell; 3] = [&mut grid.0[2][2], &mut grid.0[4][2], &mut grid.0[7][2]];
-        for cell in grid.cols()[2].iter_mut() {
-            if col_refs.contains(&(*cell as *mut Cell)) {
-                continue;
+        let col_cells = [(2, 2), (4, 2), (7, 2)];
+        for (r, c) in grid.house_cells(11) {
+            if !col_cells.contains(&(r, c)) {
+                grid[r][c].remove_candidate(2);
+                grid[r][c].remove_candidate(3);
+                grid[r][c].remove_candidate(4);
             }
-            cell.remove_candidate(2);
-            cell.remove_candidate(3);
-            cell.remove_candidate(4);
-        }
-        unsafe {
-            (*col_refs[0]).remove_candidate(3);
         }
+        grid[2][2].remove_candidate(3);
 
-        let box_refs: [*const Cell; 2] = [&grid.0[4][4], &grid.0[5][5]];
-        for cell in grid.boxes()[4].iter_mut() {
-            if box_refs.contains(&(*cell as *const Cell)) {
-                continue;
+        let box_cells = [(4, 4), (5, 5)];
+        for (r, c) in grid.house_cells(22) {
+            if !box_cells.contains(&(r, c)) {
+                grid[r][c].remove_candidate(5);
+                grid[r][c].remove_candidate(6);
             }
-            cell.remove_candidate(5);
-            cell.remove_candidate(6);
         }
 
         assert!(hidden_tuples(&mut grid));
 
-        assert!(row_refs
+        assert!(row_cells
             .iter()
-            .map(|c| unsafe { **c })
-            .all(|c| c.exact_candidates(&HashSet::from([0, 1]))));
-        assert!(col_refs
+            .all(|&(r, c)| grid[r][c].exact_candidates(&HashSet::from([0, 1]))));
+        assert!(grid[2][2].exact_candidates(&HashSet::from([2, 4])));
+        assert!(col_cells[1..]
             .iter()
-            .map(|c| unsafe { **c })
-            .enumerate()
-            .all(|(i, c)| c.exact_candidates(
-                &(if i == 0 {
-                    HashSet::from([2, 4])
-                } else {
-                    HashSet::from([2, 3, 4])
-                })
-            )));
-        assert!(box_refs
+            .all(|&(r, c)| grid[r][c].exact_candidates(&HashSet::from([2, 3, 4]))));
+        assert!(box_cells
             .iter()
-            .map(|c| unsafe { **c })
-            .all(|c| c.exact_candidates(&HashSet::from([5, 6]))));
+            .all(|&(r, c)| grid[r][c].exact_candidates(&HashSet::from([5, 6]))));
     }
 
     #[test]
     fn test_pointing_tuples() {
-        let mut grid = Grid([[Cell::default(); 9]; 9]);
-
-        let row_refs: [*const Cell; 2] = [&grid.0[0][0], &grid.0[0][1]];
-        let col_refs: [*const Cell; 3] = [&grid.0[0][2], &grid.0[1][2], &grid.0[2][2]];
-        for cell in grid.boxes()[0].iter_mut() {
-            let raw_pointer = *cell as *const Cell;
-            if !row_refs.contains(&raw_pointer) {
-                cell.remove_candidate(0);
+        let mut grid = Grid::default();
+
+        let row_cells = [(0, 0), (0, 1)];
+        let col_cells = [(0, 2), (1, 2), (2, 2)];
+        for (r, c) in grid.house_cells(18) {
+            if !row_cells.contains(&(r, c)) {
+                grid[r][c].remove_candidate(0);
             }
-            if !col_refs.contains(&raw_pointer) {
-                cell.remove_candidate(1);
+            if !col_cells.contains(&(r, c)) {
+                grid[r][c].remove_candidate(1);
             }
         }
 
         assert!(pointing_tuples(&mut grid));
 
-        assert!(grid.0[0].iter().all(|cell| {
-            row_refs.contains(&(cell as *const Cell)) == cell.candidates().contains(&0)
-        }));
+        assert!(grid
+            .house_cells(0)
+            .iter()
+            .all(|&(r, c)| row_cells.contains(&(r, c)) == grid[r][c].candidates().contains(&0)));
+
+        assert!(grid
+            .house_cells(11)
+            .iter()
+            .all(|&(r, c)| col_cells.contains(&(r, c)) == grid[r][c].candidates().contains(&1)))
+    }
+
+    #[test]
+    fn test_pointing_diagonal() {
+        let mut grid = Grid::default();
+        grid.extra_houses.extend(diagonals());
+
+        // 1 in box 1 can only go on the main diagonal, so it leaves the rest of the diagonal
+        let diagonal = [(0, 0), (1, 1), (2, 2)];
+        for (r, c) in grid.house_cells(18) {
+            if !diagonal.contains(&(r, c)) {
+                grid[r][c].remove_candidate(0);
+            }
+        }
 
-        assert!(grid.cols()[2].iter().all(
-            |cell| col_refs.contains(&(*cell as *const Cell)) == cell.candidates().contains(&1)
-        ))
+        assert!(pointing_tuples(&mut grid));
+
+        assert!((3..9).all(|i| !grid[i][i].has_candidate(0)));
+        assert!(diagonal.iter().all(|&(r, c)| grid[r][c].has_candidate(0)));
+        assert!(grid[0][8].has_candidate(0));
+        assert!(grid[3][4].has_candidate(0));
+    }
+
+    #[test]
+    fn test_patterns() {
+        let mut grid = Grid::default();
+        let pair = |a, b| Cell::Unsolved(std::array::from_fn(|n| n == a || n == b));
+        grid[0][0] = pair(0, 1);
+        grid[0][4] = pair(0, 1);
+        grid[3][8] = pair(2, 3);
+        grid[7][8] = pair(2, 3);
+        let before = grid.clone();
+
+        // Finding patterns leaves the grid alone
+        let patterns = naked_tuple_patterns(&grid);
+        assert_eq!(grid.cells, before.cells);
+        assert_eq!(
+            patterns
+                .iter()
+                .map(|p| (p.house, p.cells.clone(), p.digits.clone()))
+                .collect::<Vec<_>>(),
+            [
+                (0, vec![(0, 0), (0, 4)], vec![0, 1]),
+                (17, vec![(3, 8), (7, 8)], vec![2, 3])
+            ]
+        );
+
+        // Every pattern found on the grid is applied in the same step, including both that
+        // reach r1c9
+        assert!(naked_tuples(&mut grid));
+        for ((r, c), n) in patterns.iter().flat_map(|p| p.eliminations.clone()) {
+            assert!(!grid[r][c].candidates().contains(&n));
+        }
+        assert!(grid[0][8].exact_candidates(&HashSet::from([4, 5, 6, 7, 8])));
+        assert!(naked_tuple_patterns(&grid).is_empty());
+        assert!(!naked_tuples(&mut grid));
     }
 }
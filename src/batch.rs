@@ -0,0 +1,187 @@
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use log::{debug, error};
+
+use crate::{
+    grid::{Grid, House},
+    parser::{check_givens, parse_givens},
+    report::Status,
+    solver::Solver,
+};
+
+/// How a single puzzle was resolved
+#[derive(Clone)]
+pub enum Outcome {
+    /// Solved by the strategies alone
+    Logical(Grid),
+    /// Solved uniquely, but only with guessing
+    Backtracking(Grid),
+    Unsolvable,
+    /// More than one solution
+    Multiple,
+}
+
+/// Solves a puzzle fully, checking whether its solution is unique
+pub fn solve(solver: &Solver, mut grid: Grid) -> Outcome {
+    while solver.step(&mut grid) {}
+    if grid.broken() {
+        return Outcome::Unsolvable;
+    }
+    if grid.solved() {
+        return Outcome::Logical(grid);
+    }
+    let mut solutions = solver.solutions(&grid, 2);
+    match Status::of_solutions(&solutions) {
+        Status::Solved => Outcome::Backtracking(solutions.remove(0)),
+        Status::Multiple => Outcome::Multiple,
+        _ => Outcome::Unsolvable,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BatchStats {
+    pub logical: usize,
+    pub backtracking: usize,
+    pub unsolvable: usize,
+    pub multiple: usize,
+    /// Lines which couldn't be read as a puzzle
+    pub invalid: usize,
+    pub total_time: Duration,
+    pub slowest: Duration,
+}
+impl BatchStats {
+    pub fn puzzles(&self) -> usize {
+        self.logical + self.backtracking + self.unsolvable + self.multiple + self.invalid
+    }
+}
+impl std::fmt::Display for BatchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let average = self.total_time / self.puzzles().max(1) as u32;
+        let rows = [
+            ("Puzzles", self.puzzles().to_string()),
+            ("Solved logically", self.logical.to_string()),
+            ("Needed backtracking", self.backtracking.to_string()),
+            ("Unsolvable", self.unsolvable.to_string()),
+            ("Multiple solutions", self.multiple.to_string()),
+            ("Invalid", self.invalid.to_string()),
+            ("Total time", format!("{:.3?}", self.total_time)),
+            (
+                "Time per puzzle",
+                format!("{:.3?} (slowest {:.3?})", average, self.slowest),
+            ),
+        ];
+        for (label, value) in rows {
+            writeln!(f, "{:<21}{}", format!("{}:", label), value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Solves one puzzle per line of `input`, skipping blank lines and `#` comments, and writes a
+/// line per puzzle to `out` in the same order: the solution, or `unsolvable`, `multiple` or
+/// `invalid`
+pub fn run(
+    solver: &Solver,
+    input: &str,
+    extra_houses: &[House],
+    out: &mut impl Write,
+) -> std::io::Result<BatchStats> {
+    let mut stats = BatchStats::default();
+    let puzzles = input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for (i, line) in puzzles.enumerate() {
+        let grid = parse_givens(line).and_then(|mut grid| {
+            grid.extra_houses.extend_from_slice(extra_houses);
+            check_givens(&grid).map(|_| grid)
+        });
+        let grid = match grid {
+            Ok(grid) => grid,
+            Err(e) => {
+                error!("Puzzle {}: {}", i + 1, e);
+                stats.invalid += 1;
+                writeln!(out, "invalid")?;
+                continue;
+            }
+        };
+
+        let start = Instant::now();
+        let outcome = solve(solver, grid);
+        let elapsed = start.elapsed();
+        debug!("Puzzle {} took {:.3?}", i + 1, elapsed);
+        stats.total_time += elapsed;
+        stats.slowest = stats.slowest.max(elapsed);
+
+        match outcome {
+            Outcome::Logical(solution) => {
+                stats.logical += 1;
+                writeln!(out, "{}", solution.to_line())?;
+            }
+            Outcome::Backtracking(solution) => {
+                stats.backtracking += 1;
+                writeln!(out, "{}", solution.to_line())?;
+            }
+            Outcome::Unsolvable => {
+                stats.unsolvable += 1;
+                writeln!(out, "unsolvable")?;
+            }
+            Outcome::Multiple => {
+                stats.multiple += 1;
+                writeln!(out, "multiple")?;
+            }
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        basic_sudoku::{basic_elimination, hidden_singles, naked_singles},
+        batch::run,
+        solver::Solver,
+    };
+
+    #[test]
+    fn test_batch() {
+        let mut solver = Solver::new();
+        solver.add_strategy("naked-singles", naked_singles);
+        solver.add_strategy("basic-elimination", basic_elimination);
+        solver.add_strategy("hidden-singles", hidden_singles);
+        let input = [
+            "# classic, a hard one, an empty grid, an unsolvable one, then two 5s in a row",
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+            "",
+            ".................................................................................",
+            "12345678.........9...............................................................",
+            "55...............................................................................",
+        ]
+        .join("\n");
+        let mut out = Vec::new();
+
+        let stats = run(&solver, &input, &[], &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179\n\
+             812753649943682175675491283154237896369845721287169534521974368438526917796318452\n\
+             multiple\nunsolvable\ninvalid\n"
+        );
+        assert_eq!(stats.puzzles(), 5);
+        assert_eq!(
+            (
+                stats.logical,
+                stats.backtracking,
+                stats.multiple,
+                stats.unsolvable,
+                stats.invalid
+            ),
+            (1, 1, 1, 1, 1)
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::{collections::BTreeSet, str::FromStr};
+
+use log::trace;
+
+use crate::{
+    grid::{parse_cells, Cell, Grid},
+    sums::cage_options,
+};
+
+/// A killer cage: its digits don't repeat and, if a sum is given, total the sum
+#[derive(Clone, Debug)]
+pub struct Cage {
+    pub cells: Vec<(usize, usize)>,
+    pub sum: Option<usize>,
+}
+impl FromStr for Cage {
+    type Err = String;
+
+    /// Parses `CELLS=SUM`, or just `CELLS` for a cage without a sum, e.g. `r1c1,r1c2=10`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (cells, sum) = match s.split_once('=') {
+            Some((cells, sum)) => {
+                let sum = sum
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid cage sum `{}`", sum))?;
+                (cells, Some(sum))
+            }
+            None => (s, None),
+        };
+        let cells = parse_cells(cells)?;
+        if cells.len() > 9 {
+            return Err(format!("cage `{}` has more than 9 cells", s));
+        }
+        Ok(Cage { cells, sum })
+    }
+}
+
+pub fn killer_cages(grid: &mut Grid, cages: &[Cage]) -> bool {
+    trace!("Searching for killer cages");
+    let mut result = false;
+    for cage in cages {
+        for (r, c) in &cage.cells {
+            let Cell::Solved(n) = grid[*r][*c] else {
+                continue;
+            };
+            let allowed = std::array::from_fn(|m| m != n);
+            for (rr, cc) in cage.cells.iter().filter(|cell| **cell != (*r, *c)) {
+                result |= grid[*rr][*cc].restrict(&allowed);
+            }
+        }
+
+        let Some(sum) = cage.sum else {
+            continue;
+        };
+        let Some(options) = cage_options(grid, &cage.cells, &BTreeSet::from([sum])) else {
+            continue;
+        };
+        for ((r, c), allowed) in cage.cells.iter().zip(options.candidates.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cage_strategies::{killer_cages, Cage},
+        grid::{Cell, Grid},
+    };
+
+    #[test]
+    fn test_killer_cages() {
+        let mut grid = Grid::default();
+        grid[4][4] = Cell::Solved(8);
+        // The cage cells don't share a house, but still may not repeat a digit
+        let cages: Vec<Cage> = ["r1c1,r2c4=3", "r5c5,r9c1,r9c9"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+
+        assert!(killer_cages(&mut grid, &cages));
+
+        assert_eq!(grid[0][0].candidates(), vec![0, 1]);
+        assert_eq!(grid[1][3].candidates(), vec![0, 1]);
+        assert_eq!(grid[8][0].candidates(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!("r1c1=x".parse::<Cage>().is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use itertools::Itertools;
+
+use crate::grid::{Cell, Grid};
+
+/// Every order of the nine rows (or columns) which keeps each band of three together
+fn line_orders() -> Vec<[usize; 9]> {
+    let triples = (0..3).permutations(3).collect_vec();
+    let mut orders = vec![];
+    for bands in &triples {
+        for (a, b, c) in triples
+            .iter()
+            .cartesian_product(&triples)
+            .cartesian_product(&triples)
+            .map(|((a, b), c)| (a, b, c))
+        {
+            let within = [a, b, c];
+            orders.push(std::array::from_fn(|i| {
+                bands[i / 3] * 3 + within[i / 3][i % 3]
+            }));
+        }
+    }
+    orders
+}
+
+/// The smallest form of a classic puzzle, read as 81 characters with `.` before any digit,
+/// over every way of permuting bands, stacks, the rows within a band and columns within a
+/// stack, transposing and relabelling the digits. Puzzles which are the same up to these
+/// symmetries have the same canonical form
+pub fn canonicalize(grid: &Grid) -> String {
+    let values: [[u8; 9]; 9] = std::array::from_fn(|r| {
+        std::array::from_fn(|c| match grid[r][c] {
+            Cell::Solved(n) => n as u8 + 1,
+            Cell::Unsolved(_) => 0,
+        })
+    });
+    let transposed = std::array::from_fn(|r| std::array::from_fn(|c| values[c][r]));
+    let orders = line_orders();
+
+    let mut best = [u8::MAX; 81];
+    let mut candidate = [0; 81];
+    for values in [values, transposed] {
+        for rows in &orders {
+            for cols in &orders {
+                // Digits are numbered in order of first appearance, comparing as we go so
+                // that most arrangements are abandoned within the first row
+                let mut labels = [0; 10];
+                let mut next = 1;
+                let mut smaller = false;
+                let mut abandoned = false;
+                for i in 0..81 {
+                    let value = values[rows[i / 9]][cols[i % 9]];
+                    if value != 0 && labels[value as usize] == 0 {
+                        labels[value as usize] = next;
+                        next += 1;
+                    }
+                    candidate[i] = labels[value as usize];
+                    if !smaller {
+                        if candidate[i] > best[i] {
+                            abandoned = true;
+                            break;
+                        }
+                        smaller = candidate[i] < best[i];
+                    }
+                }
+                if !abandoned && smaller {
+                    best = candidate;
+                }
+            }
+        }
+    }
+    best.iter()
+        .map(|&n| match n {
+            0 => '.',
+            n => char::from(b'0' + n),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{canonical::canonicalize, parser::parse_grid};
+
+    #[test]
+    fn test_canonicalize() {
+        let line =
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+        let grid = parse_grid(line).unwrap();
+        let canonical = canonicalize(&grid);
+        assert_eq!(canonical.len(), 81);
+        assert_eq!(canonical.matches('.').count(), line.matches('.').count());
+
+        // Relabelling digits, swapping the first two rows and transposing changes nothing
+        let relabelled: String = line
+            .chars()
+            .map(|c| match c.to_digit(10) {
+                Some(n) => char::from_digit(n % 9 + 1, 10).unwrap(),
+                None => c,
+            })
+            .collect();
+        let mut rows = relabelled.as_bytes().chunks(9).collect::<Vec<_>>();
+        rows.swap(0, 1);
+        let swapped: Vec<u8> = rows.concat();
+        let transposed: String = (0..81)
+            .map(|i| swapped[i % 9 * 9 + i / 9] as char)
+            .collect();
+        assert_eq!(canonicalize(&parse_grid(&transposed).unwrap()), canonical);
+    }
+}
@@ -1,8 +1,14 @@
 use log::trace;
 
-use crate::grid::{Cell, Grid};
+use crate::{
+    grid::{Cell, Grid},
+    houses::diagonals,
+};
 
-trait CheckedAdd {
+const DIAGONALS: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+const ORTHOGONALS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+pub trait CheckedAdd {
     fn sudoku_add(&self, other: usize) -> Option<usize>;
 }
 impl CheckedAdd for usize {
@@ -47,7 +53,7 @@ pub fn knights(grid: &mut Grid) -> bool {
         for c in 0..9 {
             if let Cell::Solved(n) = grid[r][c] {
                 for rr in [r.checked_sub(2), r.sudoku_add(2)] {
-                    for cc in [c.checked_sub(1), r.sudoku_add(1)] {
+                    for cc in [c.checked_sub(1), c.sudoku_add(1)] {
                         let (Some(rr), Some(cc)) = (rr, cc) else {
                             continue;
                         };
@@ -60,7 +66,7 @@ pub fn knights(grid: &mut Grid) -> bool {
                     }
                 }
                 for rr in [r.checked_sub(1), r.sudoku_add(1)] {
-                    for cc in [c.checked_sub(2), r.sudoku_add(2)] {
+                    for cc in [c.checked_sub(2), c.sudoku_add(2)] {
                         let (Some(rr), Some(cc)) = (rr, cc) else {
                             continue;
                         };
@@ -78,16 +84,91 @@ pub fn knights(grid: &mut Grid) -> bool {
     result
 }
 
+/// Parses the digits a sliding piece constraint applies to, e.g. `9` or `159`
+pub fn parse_digits(s: &str) -> Result<[bool; 9], String> {
+    let mut digits = [false; 9];
+    for char in s.chars().filter(|c| *c != ',') {
+        match char.to_digit(10) {
+            Some(d @ 1..=9) => digits[d as usize - 1] = true,
+            _ => return Err(format!("invalid digit `{}` in `{}`", char, s)),
+        }
+    }
+    Ok(digits)
+}
+
+/// Removes each applicable solved digit from every cell a piece could slide to in `directions`
+fn sliding(grid: &mut Grid, directions: &[(isize, isize)], digits: &[bool; 9]) -> bool {
+    let mut result = false;
+    for r in 0..9 {
+        for c in 0..9 {
+            let Cell::Solved(n) = grid[r][c] else {
+                continue;
+            };
+            if !digits[n] {
+                continue;
+            }
+            let mut allowed = [true; 9];
+            allowed[n] = false;
+            for (dr, dc) in directions {
+                let (mut rr, mut cc) = (r, c);
+                while let (Some(r2), Some(c2)) =
+                    (rr.checked_add_signed(*dr), cc.checked_add_signed(*dc))
+                {
+                    if r2 >= 9 || c2 >= 9 {
+                        break;
+                    }
+                    (rr, cc) = (r2, c2);
+                    result |= grid[rr][cc].restrict(&allowed);
+                }
+            }
+        }
+    }
+    result
+}
+
+pub fn bishops(grid: &mut Grid, digits: &[bool; 9]) -> bool {
+    trace!("Searching for bishops");
+    sliding(grid, &DIAGONALS, digits)
+}
+
+pub fn queens(grid: &mut Grid, digits: &[bool; 9]) -> bool {
+    trace!("Searching for queens");
+    sliding(grid, &DIAGONALS, digits) | sliding(grid, &ORTHOGONALS, digits)
+}
+
+/// Rooks which only move along the two long diagonals, so the applicable digits can't repeat
+/// on either of them
+pub fn diagonal_rooks(grid: &mut Grid, digits: &[bool; 9]) -> bool {
+    trace!("Searching for rooks on the diagonals");
+    let mut result = false;
+    for diagonal in diagonals() {
+        for &(r, c) in &diagonal {
+            let Cell::Solved(n) = grid[r][c] else {
+                continue;
+            };
+            if !digits[n] {
+                continue;
+            }
+            let mut allowed = [true; 9];
+            allowed[n] = false;
+            for &(rr, cc) in diagonal.iter().filter(|cell| **cell != (r, c)) {
+                result |= grid[rr][cc].restrict(&allowed);
+            }
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
-        chess_strategies::{kings, knights},
+        chess_strategies::{bishops, diagonal_rooks, kings, knights, parse_digits, queens},
         grid::{Cell, Grid},
     };
 
     #[test]
     fn test_kings() {
-        let mut grid = Grid([[Cell::default(); 9]; 9]);
+        let mut grid = Grid::default();
 
         grid[0][0] = Cell::Solved(0);
         grid[4][4] = Cell::Solved(0);
@@ -112,7 +193,7 @@ mod test {
     }
     #[test]
     fn test_knights() {
-        let mut grid = Grid([[Cell::default(); 9]; 9]);
+        let mut grid = Grid::default();
 
         grid[0][0] = Cell::Solved(0);
         grid[4][4] = Cell::Solved(0);
@@ -125,7 +206,7 @@ mod test {
             .all(|c| matches!(c, Cell::Unsolved([false, _, _, _, _, _, _, _, _]))));
 
         assert!([
-            grid[3][2], grid[2][3], grid[5][3], grid[3][5], grid[2][5], grid[5][2], grid[5][6],
+            grid[3][2], grid[2][3], grid[6][3], grid[3][6], grid[2][5], grid[5][2], grid[5][6],
             grid[6][5]
         ]
         .iter()
@@ -135,4 +216,66 @@ mod test {
             .iter()
             .all(|c| matches!(c, Cell::Unsolved([false, _, _, _, _, _, _, _, _]))));
     }
+
+    #[test]
+    fn test_bishops() {
+        let mut grid = Grid::default();
+
+        grid[4][4] = Cell::Solved(8);
+        grid[0][0] = Cell::Solved(0);
+
+        assert!(bishops(&mut grid, &parse_digits("9").unwrap()));
+
+        assert!([grid[1][1], grid[8][8], grid[0][8], grid[8][0], grid[3][5]]
+            .iter()
+            .all(|c| matches!(c, Cell::Unsolved([_, _, _, _, _, _, _, _, false]))));
+        assert!([grid[4][0], grid[0][4], grid[3][4], grid[5][6]]
+            .iter()
+            .all(|c| matches!(c, Cell::Unsolved([_, _, _, _, _, _, _, _, true]))));
+        // 1 is not restricted, so the solved 1 in the corner eliminates nothing
+        assert!(matches!(
+            grid[1][1],
+            Cell::Unsolved([true, _, _, _, _, _, _, _, _])
+        ));
+    }
+
+    #[test]
+    fn test_queens() {
+        let mut grid = Grid::default();
+
+        grid[0][0] = Cell::Solved(8);
+        grid[2][2] = Cell::Solved(8);
+
+        assert!(queens(&mut grid, &[true; 9]));
+
+        assert!([grid[0][8], grid[8][0], grid[5][5]]
+            .iter()
+            .all(|c| matches!(c, Cell::Unsolved([_, _, _, _, _, _, _, _, false]))));
+        // Two 9s on a diagonal leave the grid broken
+        assert_eq!(grid[2][2], Cell::Unsolved([false; 9]));
+        assert!(grid.broken());
+    }
+
+    #[test]
+    fn test_diagonal_rooks() {
+        let mut grid = Grid::default();
+
+        grid[2][2] = Cell::Solved(8);
+        grid[4][4] = Cell::Solved(0);
+        grid[1][3] = Cell::Solved(8);
+
+        assert!(diagonal_rooks(&mut grid, &parse_digits("9").unwrap()));
+
+        assert!([grid[0][0], grid[1][1], grid[8][8], grid[5][5]]
+            .iter()
+            .all(|c| matches!(c, Cell::Unsolved([_, _, _, _, _, _, _, _, false]))));
+        // Only the long diagonals are affected, and only for 9s
+        assert!([grid[0][4], grid[8][0], grid[3][1]]
+            .iter()
+            .all(|c| matches!(c, Cell::Unsolved([_, _, _, _, _, _, _, _, true]))));
+        assert!(matches!(
+            grid[0][8],
+            Cell::Unsolved([true, _, _, _, _, _, _, _, _])
+        ));
+    }
 }
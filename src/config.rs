@@ -0,0 +1,144 @@
+use itertools::Itertools;
+use serde::Deserialize;
+
+use crate::{puzzle::STRATEGIES, rating::Difficulty, solver::Solver};
+
+/// Names standing for several strategies at once
+const GROUPS: &[(&str, &[&str])] = &[
+    ("singles", &["naked-singles", "hidden-singles"]),
+    (
+        "tuples",
+        &["naked-tuples", "hidden-tuples", "pointing-tuples"],
+    ),
+];
+
+/// The strategies a name or group stands for, accepting `_` in place of `-`
+fn expand(name: &str) -> Result<Vec<&'static str>, String> {
+    let name = name.trim().to_ascii_lowercase().replace('_', "-");
+    if let Some((_, members)) = GROUPS.iter().find(|(group, _)| *group == name) {
+        return Ok(members.to_vec());
+    }
+    match STRATEGIES.iter().find(|strategy| **strategy == name) {
+        Some(strategy) => Ok(vec![*strategy]),
+        None => Err(format!(
+            "unknown strategy `{}`, expected one of {}",
+            name,
+            GROUPS
+                .iter()
+                .map(|(group, _)| *group)
+                .chain(STRATEGIES.iter().copied())
+                .join(", ")
+        )),
+    }
+}
+
+/// Checks a strategy or group name given as a flag
+pub fn parse_strategy(name: &str) -> Result<String, String> {
+    expand(name)?;
+    Ok(name.to_string())
+}
+
+/// Which strategies are used for logical steps, and in what order
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct StrategyConfig {
+    /// The strategies to use, in order. Defaults to every strategy the puzzle needs
+    pub strategies: Option<Vec<String>>,
+    #[serde(default)]
+    pub disable: Vec<String>,
+    /// Leaves out every strategy in a harder tier than this one, as ranked by
+    /// `Difficulty::of_strategy`
+    pub max_technique: Option<String>,
+}
+impl StrategyConfig {
+    pub fn from_toml(s: &str) -> Result<StrategyConfig, String> {
+        toml::from_str(s).map_err(|e| e.to_string())
+    }
+    /// Restricts and reorders the logical steps of `solver`
+    pub fn apply(&self, solver: &mut Solver) -> Result<(), String> {
+        let mut order: Vec<&str> = match &self.strategies {
+            Some(names) => {
+                let mut order = vec![];
+                for name in names {
+                    for strategy in expand(name)? {
+                        if !order.contains(&strategy) {
+                            order.push(strategy);
+                        }
+                    }
+                }
+                order
+            }
+            None => solver.names().collect(),
+        };
+        for name in &self.disable {
+            let disabled = expand(name)?;
+            order.retain(|strategy| !disabled.contains(strategy));
+        }
+        if let Some(name) = &self.max_technique {
+            let hardest = expand(name)?
+                .into_iter()
+                .map(Difficulty::of_strategy)
+                .max()
+                .unwrap_or(Difficulty::Easy);
+            order.retain(|strategy| Difficulty::of_strategy(strategy) <= hardest);
+        }
+        solver.set_order(&order);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+
+    use crate::{
+        config::StrategyConfig,
+        parser::parse_grid,
+        puzzle::{Constraint, Puzzle},
+    };
+
+    #[test]
+    fn test_apply() {
+        let puzzle = Puzzle {
+            grid: Default::default(),
+            constraints: vec![Constraint::Antiking],
+        };
+
+        let mut solver = puzzle.solver();
+        let config = StrategyConfig {
+            strategies: Some(vec!["basic_elimination".into(), "singles".into()]),
+            disable: vec!["hidden-singles".into()],
+            max_technique: None,
+        };
+        config.apply(&mut solver).unwrap();
+        assert_eq!(
+            solver.names().collect_vec(),
+            ["basic-elimination", "naked-singles"]
+        );
+
+        let mut solver = puzzle.solver();
+        let config = StrategyConfig::from_toml(r#"max-technique = "pointing-tuples""#).unwrap();
+        config.apply(&mut solver).unwrap();
+        assert_eq!(
+            solver.names().collect_vec(),
+            [
+                "naked-singles",
+                "basic-elimination",
+                "hidden-singles",
+                "pointing-tuples"
+            ]
+        );
+
+        // Backtracking still enforces the rules of disabled strategies
+        let grid = parse_grid(&".".repeat(81)).unwrap();
+        let solution = solver.solutions(&grid, 1).pop().unwrap();
+        assert!((0..8).all(|c| solution[0][c] != solution[1][c + 1]));
+
+        let config = StrategyConfig {
+            strategies: Some(vec!["xwing".into()]),
+            ..Default::default()
+        };
+        assert!(config.apply(&mut solver).is_err());
+        assert!(StrategyConfig::from_toml("order = []").is_err());
+    }
+}
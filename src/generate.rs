@@ -0,0 +1,152 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use itertools::Itertools;
+use log::debug;
+use serde::Serialize;
+
+use crate::{
+    grid::{Cell, Grid},
+    solver::Solver,
+};
+
+/// A small xorshift generator, so that puzzles can be reproduced from their seed
+pub struct Rng(u64);
+impl Rng {
+    /// A seed taken from the current time, for when none is given
+    pub fn time_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(1, |time| time.as_nanos() as u64)
+    }
+    pub fn new(seed: u64) -> Rng {
+        // Zero is a fixed point of xorshift
+        Rng(seed.max(1))
+    }
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    /// A number in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Generated {
+    pub puzzle: String,
+    pub solution: String,
+    pub seed: u64,
+}
+
+/// Makes a puzzle with a unique solution by adding random givens to `template` until the
+/// solution is unique, then removing any given which isn't needed for that. Returns `None` if
+/// the template has no solutions
+pub fn generate(solver: &Solver, template: &Grid, rng: &mut Rng) -> Option<(Grid, Grid)> {
+    if solver.solutions(template, 1).is_empty() {
+        return None;
+    }
+    let mut puzzle = template.clone();
+    // Digits which left no solutions. Givens are only added in this loop, so they stay ruled out
+    let mut ruled_out = vec![];
+    loop {
+        if solver.expired() {
+            return None;
+        }
+        let mut stepped = puzzle.clone();
+        while solver.step(&mut stepped) {}
+        let unsolved = (0..9)
+            .cartesian_product(0..9)
+            .filter(|&(r, c)| matches!(puzzle[r][c], Cell::Unsolved(_)))
+            .filter_map(|(r, c)| {
+                let mut options = stepped[r][c].options();
+                options.retain(|n| !ruled_out.contains(&((r, c), *n)));
+                (!options.is_empty()).then_some(((r, c), options))
+            })
+            .collect_vec();
+        if unsolved.is_empty() {
+            return None;
+        }
+        let ((r, c), options) = &unsolved[rng.below(unsolved.len())];
+        let previous = puzzle[*r][*c];
+        let digit = options[rng.below(options.len())];
+        puzzle[*r][*c] = Cell::Solved(digit);
+        match solver.solutions(&puzzle, 2).len() {
+            0 => {
+                puzzle[*r][*c] = previous;
+                ruled_out.push(((*r, *c), digit));
+            }
+            1 => break,
+            _ => {}
+        }
+    }
+
+    let mut givens = (0..9)
+        .cartesian_product(0..9)
+        .filter(|&(r, c)| {
+            matches!(puzzle[r][c], Cell::Solved(_)) && !matches!(template[r][c], Cell::Solved(_))
+        })
+        .collect_vec();
+    debug!("Found a unique puzzle with {} givens", givens.len());
+    rng.shuffle(&mut givens);
+    for (r, c) in givens {
+        let given = puzzle[r][c];
+        puzzle[r][c] = template[r][c];
+        if solver.solutions(&puzzle, 2).len() != 1 {
+            puzzle[r][c] = given;
+        }
+    }
+    let solution = solver.solutions(&puzzle, 1).pop()?;
+    Some((puzzle, solution))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        generate::{generate, Rng},
+        grid::{Cell, Grid},
+        parser::parse_grid,
+        puzzle::Puzzle,
+    };
+
+    #[test]
+    fn test_generate() {
+        let solver = Puzzle {
+            grid: Grid::default(),
+            constraints: vec![],
+        }
+        .solver();
+
+        // Starting from a few full rows keeps this quick
+        let template = parse_grid(&format!(
+            "{}{}",
+            "534678912672195348198342567859761423",
+            ".".repeat(45)
+        ))
+        .unwrap();
+
+        let (puzzle, solution) = generate(&solver, &template, &mut Rng::new(7)).unwrap();
+
+        assert_eq!(solver.solutions(&puzzle, 2).len(), 1);
+        assert!(solution.solved() && !solution.broken());
+        for (r, c) in (0..9).flat_map(|r| (0..9).map(move |c| (r, c))) {
+            if let Cell::Solved(n) = puzzle[r][c] {
+                assert_eq!(solution[r][c], Cell::Solved(n));
+            }
+        }
+        // The same seed gives the same puzzle
+        let (again, _) = generate(&solver, &template, &mut Rng::new(7)).unwrap();
+        assert_eq!(again.to_line(), puzzle.to_line());
+
+        // r1c9 can only be 9, which r2c9 already is
+        let unsolvable = parse_grid(&format!("12345678.........9{}", ".".repeat(63))).unwrap();
+        assert!(generate(&solver, &unsolvable, &mut Rng::new(7)).is_none());
+    }
+}
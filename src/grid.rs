@@ -3,6 +3,8 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
+use itertools::Itertools;
+
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 pub enum Cell {
     Solved(usize),
@@ -29,13 +31,43 @@ impl Cell {
             Vec::new()
         }
     }
+    /// Digits this cell could hold, including its value if it is already solved
+    pub fn options(&self) -> Vec<usize> {
+        match self {
+            Cell::Solved(n) => vec![*n],
+            Cell::Unsolved(_) => self.candidates(),
+        }
+    }
+    /// Removes every candidate not marked in `allowed`, returning whether anything changed.
+    /// A solved cell whose value is not allowed is emptied, so the grid reports itself as broken
+    pub fn restrict(&mut self, allowed: &[bool; 9]) -> bool {
+        match self {
+            Cell::Solved(n) => {
+                if allowed[*n] {
+                    return false;
+                }
+                *self = Cell::Unsolved([false; 9]);
+                true
+            }
+            Cell::Unsolved(cands) => {
+                let mut result = false;
+                for (cand, allowed) in cands.iter_mut().zip(allowed) {
+                    if *cand && !allowed {
+                        *cand = false;
+                        result = true;
+                    }
+                }
+                result
+            }
+        }
+    }
 }
 impl core::fmt::Display for Cell {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         if let Cell::Solved(n) = self {
             f.write_str(&(n + 1).to_string())
         } else {
-            f.write_str(&"_")
+            f.write_str("_")
         }
     }
 }
@@ -52,7 +84,17 @@ impl From<Option<usize>> for Cell {
     }
 }
 
-pub struct Grid(pub [[Cell; 9]; 9]);
+/// A set of nine cells which must all hold different digits
+pub type House = [(usize, usize); 9];
+
+#[derive(Clone, Default)]
+pub struct Grid {
+    pub cells: [[Cell; 9]; 9],
+    /// Houses beyond the rows, columns and boxes, such as the diagonals of Sudoku-X
+    pub extra_houses: Vec<House>,
+    /// Irregular (jigsaw) regions replacing the boxes, as a region index 0-8 for every cell
+    pub regions: Option<[[usize; 9]; 9]>,
+}
 impl std::fmt::Display for Grid {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         for (i, line) in self.into_iter().enumerate() {
@@ -76,48 +118,151 @@ impl Deref for Grid {
     type Target = [[Cell; 9]; 9];
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.cells
     }
 }
 impl DerefMut for Grid {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.cells
     }
 }
 impl Grid {
-    pub fn solved(&self) -> bool {
+    /// Writes the grid as 81 characters row by row, with `.` for each unsolved cell
+    pub fn to_line(&self) -> String {
         self.iter()
             .flatten()
-            .all(|c| if let Cell::Solved(_) = c { true } else { false })
+            .map(|cell| match cell {
+                Cell::Solved(n) => char::from_digit(*n as u32 + 1, 10).unwrap_or('.'),
+                Cell::Unsolved(_) => '.',
+            })
+            .collect()
     }
+
+    /// Writes the grid as a 9×9 block of candidate strings with box separators, where a solved
+    /// cell is its digit and a cell with no candidates left is `-`.
+    /// As in other tools' pencil marks, an unsolved cell with one candidate left is also just
+    /// its digit, so it reads back as solved
+    pub fn to_pencil_marks(&self) -> String {
+        let marks = self.map(|row| {
+            row.map(|cell| match cell {
+                Cell::Unsolved(cands) if cands.iter().all(|c| !c) => "-".to_string(),
+                cell => cell.options().iter().map(|n| n + 1).join(""),
+            })
+        });
+        let widths: Vec<_> = (0..9)
+            .map(|c| marks.iter().map(|row| row[c].len()).max().unwrap_or(1))
+            .collect();
+        let separator = format!(
+            "+{}+\n",
+            widths
+                .chunks(3)
+                .map(|w| "-".repeat(w.iter().sum::<usize>() + 2 * w.len()))
+                .join("+")
+        );
+
+        let mut out = separator.clone();
+        for (r, row) in marks.iter().enumerate() {
+            if r % 3 == 0 && r > 0 {
+                out += &separator;
+            }
+            let mut boxes = row.chunks(3).zip(widths.chunks(3)).map(|(cells, widths)| {
+                cells
+                    .iter()
+                    .zip(widths)
+                    .map(|(marks, w)| format!(" {:<w$} ", marks))
+                    .join("")
+            });
+            out += &format!("|{}|\n", boxes.join("|"));
+        }
+        out + &separator
+    }
+
+    pub fn solved(&self) -> bool {
+        self.iter().flatten().all(|c| matches!(c, Cell::Solved(_)))
+    }
+    /// Whether any cell has run out of candidates, or two solved cells in a house conflict
     pub fn broken(&self) -> bool {
-        self.iter().flatten().any(|cell| {
+        let empty = self.iter().flatten().any(|cell| {
             if let Cell::Unsolved(cands) = cell {
                 cands.iter().all(|t| !t)
             } else {
                 false
             }
+        });
+        empty || (0..81).tuple_combinations().any(|(a, b)| {
+            let (a, b) = ((a / 9, a % 9), (b / 9, b % 9));
+            matches!((self[a.0][a.1], self[b.0][b.1]), (Cell::Solved(x), Cell::Solved(y)) if x == y)
+                && self.sees(a, b)
         })
     }
 
-    pub fn cols(&mut self) -> Vec<Vec<&mut Cell>> {
-        self.iter_mut().flatten().enumerate().fold(
-            (0..9).map(|_| Vec::new()).collect(),
-            |mut p, (i, c)| {
-                p.get_mut(i % 9).unwrap().push(c);
-                p
-            },
-        )
-    }
-    pub fn boxes(&mut self) -> Vec<Vec<&mut Cell>> {
-        self.iter_mut().flatten().enumerate().fold(
-            (0..9).map(|_| Vec::new()).collect(),
-            |mut p, (i, c)| {
-                let row = i / 27;
-                let col = (i % 9) / 3;
-                p.get_mut(col + row * 3).unwrap().push(c);
-                p
-            },
-        )
+    /// Whether two distinct cells share a house
+    pub fn sees(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        a != b
+            && (a.0 == b.0
+                || a.1 == b.1
+                || self.region(a) == self.region(b)
+                || self
+                    .extra_houses
+                    .iter()
+                    .any(|house| house.contains(&a) && house.contains(&b)))
+    }
+
+    /// Index of the box, or jigsaw region, containing a cell
+    pub fn region(&self, (r, c): (usize, usize)) -> usize {
+        match &self.regions {
+            Some(regions) => regions[r][c],
+            None => r / 3 * 3 + c / 3,
+        }
     }
+
+    /// Number of houses: 9 rows, 9 columns, 9 boxes and then any extra houses
+    pub fn house_count(&self) -> usize {
+        27 + self.extra_houses.len()
+    }
+    /// Coordinates of the cells in a house, indexed as for `house_count`
+    pub fn house_cells(&self, i: usize) -> House {
+        if let (18..=26, Some(regions)) = (i, &self.regions) {
+            let mut cells = (0..9)
+                .cartesian_product(0..9)
+                .filter(|(r, c)| regions[*r][*c] == i - 18);
+            // `puzzle::region_map` checks that every region has nine cells
+            return std::array::from_fn(|_| cells.next().expect("a jigsaw region is short"));
+        }
+        std::array::from_fn(|j| match i {
+            0..=8 => (i, j),
+            9..=17 => (j, i - 9),
+            18..=26 => ((i - 18) / 3 * 3 + j / 3, (i - 18) % 3 * 3 + j % 3),
+            _ => self.extra_houses[i - 27][j],
+        })
+    }
+    pub fn house(&mut self, i: usize) -> Vec<&mut Cell> {
+        let cells = self.house_cells(i);
+        self.iter_mut()
+            .flatten()
+            .enumerate()
+            .filter_map(|(j, c)| cells.contains(&(j / 9, j % 9)).then_some(c))
+            .collect()
+    }
+}
+
+/// Parses a 1-indexed cell reference such as `r3c4` into a 0-indexed (row, col) pair
+pub fn parse_cell(s: &str) -> Result<(usize, usize), String> {
+    let lower = s.trim().to_ascii_lowercase();
+    let coords = lower
+        .strip_prefix('r')
+        .and_then(|rest| rest.split_once('c'))
+        .and_then(|(r, c)| Some((r.parse::<usize>().ok()?, c.parse::<usize>().ok()?)));
+    match coords {
+        Some((r, c)) if (1..=9).contains(&r) && (1..=9).contains(&c) => Ok((r - 1, c - 1)),
+        _ => Err(format!(
+            "invalid cell reference `{}`, expected e.g. `r3c4`",
+            s.trim()
+        )),
+    }
+}
+
+/// Parses a comma-separated list of cell references
+pub fn parse_cells(s: &str) -> Result<Vec<(usize, usize)>, String> {
+    s.split(',').map(parse_cell).collect()
 }
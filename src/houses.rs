@@ -0,0 +1,65 @@
+use crate::grid::House;
+
+/// Both main diagonals, as in Sudoku-X
+pub fn diagonals() -> Vec<House> {
+    vec![
+        std::array::from_fn(|i| (i, i)),
+        std::array::from_fn(|i| (i, 8 - i)),
+    ]
+}
+
+/// The four extra 3x3 boxes of Windoku, each one cell in from a corner of the grid
+pub fn windoku() -> Vec<House> {
+    [(1, 1), (1, 5), (5, 1), (5, 5)]
+        .into_iter()
+        .map(|(r, c)| std::array::from_fn(|i| (r + i / 3, c + i % 3)))
+        .collect()
+}
+
+/// Disjoint groups: the cells at the same position within every box
+pub fn disjoint_groups() -> Vec<House> {
+    (0..9)
+        .map(|p| std::array::from_fn(|b| (b / 3 * 3 + p / 3, b % 3 * 3 + p % 3)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        basic_sudoku::{basic_elimination, hidden_singles},
+        grid::{Cell, Grid},
+        houses::{diagonals, disjoint_groups, windoku},
+    };
+
+    #[test]
+    fn test_diagonal_houses() {
+        let mut grid = Grid::default();
+        grid.extra_houses.extend(diagonals());
+        grid[0][0] = Cell::Solved(0);
+        for i in 1..8 {
+            grid[i][i].remove_candidate(1);
+        }
+
+        assert!(basic_elimination(&mut grid));
+        assert!(!grid[8][8].candidates().contains(&0));
+        assert!(!grid[4][4].candidates().contains(&0));
+        assert!(grid[8][1].candidates().contains(&0));
+
+        assert!(hidden_singles(&mut grid));
+        assert_eq!(grid[8][8].candidates(), vec![1]);
+    }
+
+    #[test]
+    fn test_extra_house_shapes() {
+        let mut grid = Grid::default();
+        grid.extra_houses.extend(windoku());
+        grid.extra_houses.extend(disjoint_groups());
+
+        assert!(grid.sees((1, 1), (3, 3)));
+        assert!(grid.sees((5, 7), (7, 5)));
+        assert!(!grid.sees((0, 0), (4, 4)));
+        assert!(grid.sees((0, 0), (6, 3)));
+        assert!(grid.sees((2, 1), (8, 7)));
+        assert!(!grid.sees((2, 1), (8, 8)));
+    }
+}
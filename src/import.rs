@@ -0,0 +1,438 @@
+use log::warn;
+use serde_json::{Map, Value};
+
+use crate::{
+    adjacency_strategies::parse_pair,
+    chess_strategies::parse_digits,
+    grid::{parse_cell, parse_cells, Cell, Grid},
+    houses::diagonals,
+    lz_string::decompress_from_base64,
+    puzzle::{region_map, Constraint, Puzzle},
+};
+
+/// Whether `s` looks like a puzzle link rather than a file path
+pub fn is_link(s: &str) -> bool {
+    s.starts_with("https://") || s.starts_with("http://")
+}
+
+/// Reads an f-puzzles or SudokuPad link, the compressed data from one, or f-puzzles JSON.
+/// Everything is decoded locally, so short links stored on those sites can't be read
+pub fn import(input: &str) -> Result<Puzzle, String> {
+    let input = input.trim();
+    let json = if input.starts_with('{') {
+        input.to_string()
+    } else {
+        decompress_from_base64(&link_data(input)?)?
+    };
+    let value = serde_json::from_str::<Value>(&json)
+        .map_err(|e| format!("invalid f-puzzles data: {}", e))?;
+    match value.as_object() {
+        Some(object) => from_fpuzzles(object),
+        None => Err("f-puzzles data is not a JSON object".to_string()),
+    }
+}
+
+/// Extracts the compressed f-puzzles data from a link, undoing URL escaping
+fn link_data(input: &str) -> Result<String, String> {
+    let data = if !is_link(input) {
+        input
+    } else if let Some((_, data)) = input.split_once("load=") {
+        data
+    } else if input.contains("sudokupad") || input.contains("crackingthecryptic") {
+        let path = input.split_once("://").map_or(input, |(_, rest)| rest);
+        let path = path.split_once('/').map_or("", |(_, path)| path);
+        let path = path.strip_prefix("sudoku/").unwrap_or(path);
+        let payload = path
+            .split_once("puzzle=")
+            .map_or(path.trim_start_matches('?'), |(_, payload)| payload);
+        if let Some(data) = payload.strip_prefix("fpuzzles") {
+            data
+        } else if payload.starts_with("scl") || payload.starts_with("ctc") {
+            return Err(
+                "SudokuPad's own puzzle format isn't supported, share the puzzle as an f-puzzles link instead"
+                    .to_string(),
+            );
+        } else {
+            return Err(format!(
+                "`{}` is a short link stored on the SudokuPad server, which can't be decoded locally",
+                input
+            ));
+        }
+    } else if input.contains("f-puzzles") {
+        return Err(format!(
+            "`{}` has no puzzle data; short links stored on the f-puzzles server can't be decoded locally",
+            input
+        ));
+    } else {
+        return Err(format!("`{}` is not an f-puzzles or SudokuPad link", input));
+    };
+    let data = data.split(['&', '?', '#']).next().unwrap_or_default();
+    Ok(data
+        .replace("%2B", "+")
+        .replace("%2F", "/")
+        .replace("%3D", "=")
+        .replace(' ', "+"))
+}
+
+/// The array of elements stored under `key`, each an object
+fn elements<'a>(key: &str, value: &'a Value) -> Result<Vec<&'a Map<String, Value>>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("f-puzzles `{}` is not a list", key))?
+        .iter()
+        .map(|element| {
+            element
+                .as_object()
+                .ok_or_else(|| format!("f-puzzles `{}` holds an invalid element", key))
+        })
+        .collect()
+}
+
+/// A list of cells, joined into a comma-separated spec
+fn cell_list(key: &str, cells: Option<&Value>) -> Result<String, String> {
+    let cells = cells
+        .and_then(Value::as_array)
+        .ok_or_else(|| format!("f-puzzles `{}` has an invalid cell list", key))?;
+    cells
+        .iter()
+        .map(|cell| {
+            cell.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("f-puzzles `{}` has an invalid cell", key))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|cells| cells.join(","))
+}
+fn cells(key: &str, element: &Map<String, Value>) -> Result<String, String> {
+    cell_list(key, element.get("cells"))
+}
+fn cell(key: &str, element: &Map<String, Value>) -> Result<String, String> {
+    element
+        .get("cell")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("f-puzzles `{}` is missing its cell", key))
+}
+/// Each of the element's lines, for elements which may hold several
+fn lines(key: &str, element: &Map<String, Value>) -> Result<Vec<String>, String> {
+    let lines = element
+        .get("lines")
+        .and_then(Value::as_array)
+        .ok_or_else(|| format!("f-puzzles `{}` is missing its lines", key))?;
+    lines
+        .iter()
+        .map(|line| cell_list(key, Some(line)))
+        .collect()
+}
+
+/// A field holding a clue value, which f-puzzles stores as text; empty values count as absent
+fn number(element: &Map<String, Value>, field: &str) -> Option<String> {
+    match element.get(field)? {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn spec<T: std::str::FromStr<Err = String>>(key: &str, spec: &str) -> Result<T, String> {
+    spec.parse()
+        .map_err(|e| format!("f-puzzles `{}` element: {}", key, e))
+}
+
+fn from_fpuzzles(object: &Map<String, Value>) -> Result<Puzzle, String> {
+    let size = object.get("size").and_then(Value::as_u64).unwrap_or(9);
+    if size != 9 {
+        return Err(format!(
+            "only 9x9 puzzles are supported, found size {}",
+            size
+        ));
+    }
+    let rows = object
+        .get("grid")
+        .and_then(Value::as_array)
+        .filter(|rows| rows.len() == 9)
+        .ok_or("f-puzzles data has no 9x9 grid")?;
+
+    let mut grid = Grid::default();
+    let mut regions = Vec::new();
+    for (r, row) in rows.iter().enumerate() {
+        let row = row
+            .as_array()
+            .filter(|row| row.len() == 9)
+            .ok_or("f-puzzles data has no 9x9 grid")?;
+        for (c, cell) in row.iter().enumerate() {
+            let given = cell.get("given").and_then(Value::as_bool) == Some(true);
+            match cell.get("value").and_then(Value::as_u64) {
+                Some(d @ 1..=9) if given => grid[r][c] = Cell::Solved(d as usize - 1),
+                _ => {}
+            }
+            if let Some(marks) = cell.get("givenPencilMarks").and_then(Value::as_array) {
+                let digits = marks.iter().map(|d| d.to_string()).collect::<String>();
+                grid[r][c].restrict(&parse_digits(&digits)?);
+            }
+            let region = cell.get("region").and_then(Value::as_u64);
+            regions.push(region.map_or(r / 3 * 3 + c / 3, |region| region as usize));
+        }
+    }
+    if regions
+        .iter()
+        .enumerate()
+        .any(|(i, region)| *region != grid.region((i / 9, i % 9)))
+    {
+        grid.regions = Some(region_map(&regions)?);
+    }
+
+    let mut constraints = Vec::new();
+    for (key, value) in object {
+        let enabled = value.as_bool() == Some(true);
+        let empty = value.as_bool() == Some(false)
+            || value.as_array().is_some_and(|a| a.is_empty())
+            || value.is_null();
+        match key.as_str() {
+            "size"
+            | "grid"
+            | "title"
+            | "author"
+            | "ruleset"
+            | "solution"
+            | "disabledlogic"
+            | "truecandidatesoptions" => {}
+            _ if empty => {}
+            "antiknight" if enabled => constraints.push(Constraint::Antiknight),
+            "antiking" if enabled => constraints.push(Constraint::Antiking),
+            "nonconsecutive" if enabled => constraints.push(Constraint::Nonconsecutive),
+            "disjointgroups" if enabled => constraints.push(Constraint::DisjointGroups),
+            "diagonal-" if enabled => grid.extra_houses.push(diagonals()[0]),
+            "diagonal+" if enabled => grid.extra_houses.push(diagonals()[1]),
+            "negative" => {
+                let kinds = value
+                    .as_array()
+                    .map(|kinds| kinds.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+                    .unwrap_or_default();
+                match (kinds.contains(&"ratio"), kinds.contains(&"difference")) {
+                    (true, true) => constraints.push(Constraint::AllDots),
+                    (false, false) => {}
+                    _ => return Err(
+                        "the negative constraint for only one colour of kropki dot isn't supported"
+                            .to_string(),
+                    ),
+                }
+                if kinds.contains(&"xv") {
+                    constraints.push(Constraint::AllXv);
+                }
+            }
+            "killercage" => {
+                for element in elements(key, value)? {
+                    let cells = cells(key, element)?;
+                    let cage = match number(element, "value") {
+                        Some(sum) => format!("{}={}", cells, sum),
+                        None => cells,
+                    };
+                    constraints.push(Constraint::Cage {
+                        spec: spec(key, &cage)?,
+                    });
+                }
+            }
+            "arrow" => {
+                for element in elements(key, value)? {
+                    let circle = cells(key, element)?;
+                    let circle_cells = parse_cells(&circle)?;
+                    let mut shaft = Vec::new();
+                    for line in lines(key, element)? {
+                        for cell in line.split(',') {
+                            if !circle_cells.contains(&parse_cell(cell)?) {
+                                shaft.push(cell.to_string());
+                            }
+                        }
+                    }
+                    let arrow = format!("{}={}", circle, shaft.join(","));
+                    constraints.push(Constraint::Arrow {
+                        spec: spec(key, &arrow)?,
+                    });
+                }
+            }
+            "thermometer" | "palindrome" | "renban" | "whispers" | "betweenline"
+            | "regionsumline" => {
+                for element in elements(key, value)? {
+                    for line in lines(key, element)? {
+                        let spec = spec(key, &line)?;
+                        constraints.push(match key.as_str() {
+                            "thermometer" => Constraint::Thermo { spec },
+                            "palindrome" => Constraint::Palindrome { spec },
+                            "renban" => Constraint::Renban { spec },
+                            "whispers" => Constraint::GermanWhispers { spec },
+                            "betweenline" => Constraint::Between { spec },
+                            _ => Constraint::RegionSum { spec },
+                        });
+                    }
+                }
+            }
+            "littlekillersum" => {
+                for element in elements(key, value)? {
+                    let Some(sum) = number(element, "value") else {
+                        continue;
+                    };
+                    let cell = cell(key, element)?;
+                    let direction = match element.get("direction").and_then(Value::as_str) {
+                        Some("UR") => "ne",
+                        Some("UL") => "nw",
+                        Some("DR") => "se",
+                        Some("DL") => "sw",
+                        _ => return Err("f-puzzles little killer has no direction".to_string()),
+                    };
+                    let clue = format!("{}:{}={}", cell, direction, sum);
+                    constraints.push(Constraint::LittleKiller {
+                        spec: spec(key, &clue)?,
+                    });
+                }
+            }
+            "xsum" | "skyscraper" => {
+                for element in elements(key, value)? {
+                    let Some(clue) = number(element, "value") else {
+                        continue;
+                    };
+                    let cell = cell(key, element)?;
+                    let spec = spec(key, &format!("{}={}", cell, clue))?;
+                    constraints.push(match key.as_str() {
+                        "xsum" => Constraint::XSum { spec },
+                        _ => Constraint::Skyscraper { spec },
+                    });
+                }
+            }
+            "odd" | "even" => {
+                for element in elements(key, value)? {
+                    let cell = cell(key, element)?;
+                    constraints.push(Constraint::Overlay {
+                        spec: spec(key, &format!("{}:{}", key, cell))?,
+                    });
+                }
+            }
+            "quadruple" => {
+                for element in elements(key, value)? {
+                    let cells = parse_cells(&cells(key, element)?)?;
+                    let (r, c) = cells.iter().min().copied().unwrap_or_default();
+                    let digits = (element.get("values").and_then(Value::as_array))
+                        .map(|values| values.iter().map(|d| d.to_string()).collect::<String>())
+                        .unwrap_or_default();
+                    let quad = format!("quad:r{}c{}={}", r + 1, c + 1, digits);
+                    constraints.push(Constraint::Overlay {
+                        spec: spec(key, &quad)?,
+                    });
+                }
+            }
+            "difference" | "ratio" | "xv" => {
+                for element in elements(key, value)? {
+                    let pair = parse_pair(&cells(key, element)?)?;
+                    let marker = number(element, "value");
+                    constraints.push(match (key.as_str(), marker.as_deref()) {
+                        ("difference", None | Some("1")) => Constraint::WhiteDot { spec: pair },
+                        ("ratio", None | Some("2")) => Constraint::BlackDot { spec: pair },
+                        ("xv", Some("X" | "x")) => Constraint::XPair { spec: pair },
+                        ("xv", Some("V" | "v")) => Constraint::VPair { spec: pair },
+                        (_, marker) => {
+                            return Err(format!(
+                                "unsupported f-puzzles `{}` value `{}`",
+                                key,
+                                marker.unwrap_or_default()
+                            ))
+                        }
+                    });
+                }
+            }
+            "extraregion" => {
+                for element in elements(key, value)? {
+                    let cells = parse_cells(&cells(key, element)?)?;
+                    let house = cells
+                        .try_into()
+                        .map_err(|_| "f-puzzles extra regions must have 9 cells".to_string())?;
+                    grid.extra_houses.push(house);
+                }
+            }
+            // Purely decorative, though a puzzle's rules may give them a meaning
+            "text" | "circle" | "rectangle" | "line" | "cage" => {
+                warn!(
+                    "Ignoring f-puzzles cosmetic `{}` elements, check the rules don't depend on them",
+                    key
+                );
+            }
+            _ => return Err(format!("unsupported f-puzzles element `{}`", key)),
+        }
+    }
+
+    Ok(Puzzle { grid, constraints })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::Cell,
+        import::{import, link_data},
+        puzzle::Constraint,
+    };
+
+    #[test]
+    fn test_import() {
+        let mut grid = vec![vec![r#"{}"#.to_string(); 9]; 9];
+        grid[0][0] = r#"{"value":5,"given":true}"#.to_string();
+        grid[0][1] = r#"{"value":3}"#.to_string();
+        grid[8][8] = r#"{"givenPencilMarks":[1,2]}"#.to_string();
+        let rows = grid
+            .iter()
+            .map(|row| format!("[{}]", row.join(",")))
+            .collect::<Vec<_>>()
+            .join(",");
+        let json = format!(
+            r#"{{"size":9,"title":"Test","grid":[{}],"antiknight":true,"antiking":false,
+            "killercage":[{{"cells":["R1C2","R1C3"],"value":"10"}}],
+            "arrow":[{{"cells":["R5C5"],"lines":[["R5C5","R5C6","R5C7"]]}}],
+            "thermometer":[{{"lines":[["R9C1","R9C2"],["R9C1","R8C1"]]}}],
+            "littlekillersum":[{{"cell":"R0C1","direction":"DR","value":"45"}}],
+            "ratio":[{{"cells":["R2C1","R2C2"]}}],"negative":[],"diagonal+":true,"text":[{{}}]}}"#,
+            rows
+        );
+
+        let puzzle = import(&json).unwrap();
+
+        assert_eq!(puzzle.grid[0][0], Cell::Solved(4));
+        assert_eq!(puzzle.grid[0][1], Cell::Unsolved([true; 9]));
+        assert_eq!(puzzle.grid[8][8].candidates(), vec![0, 1]);
+        assert_eq!(puzzle.grid.extra_houses.len(), 1);
+        assert!(puzzle.grid.regions.is_none());
+        let arrow = puzzle
+            .constraints
+            .iter()
+            .find_map(|constraint| match constraint {
+                Constraint::Arrow { spec } => Some(spec),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(arrow.shaft, vec![(4, 5), (4, 6)]);
+        // Antiknight, the cage, the arrow, two thermometers, the little killer and the dot
+        assert_eq!(puzzle.constraints.len(), 7);
+
+        let unsupported = json.replace(r#""antiking":false"#, r#""sandwichsum":[{}]"#);
+        assert!(import(&unsupported).is_err_and(|e| e.contains("sandwichsum")));
+    }
+
+    #[test]
+    fn test_links() {
+        // {"size":9,"grid":[]} compressed by lz-string
+        let data = "N4IgzglgXgpiBcBOANCA5gJwgEwQbQF0BfIA";
+        for link in [
+            format!("https://www.f-puzzles.com/?load={}", data),
+            format!("https://sudokupad.app/fpuzzles{}?setting-nogrid=1", data),
+            format!(
+                "https://app.crackingthecryptic.com/sudoku/?puzzle=fpuzzles{}",
+                data
+            ),
+        ] {
+            assert_eq!(link_data(&link).unwrap(), data);
+        }
+        assert_eq!(link_data("N4Ig%2B").unwrap(), "N4Ig+");
+        assert!(link_data("https://sudokupad.app/sclN4Ig").is_err());
+        assert!(link_data("https://sudokupad.app/abc123").is_err());
+        assert!(link_data("https://f-puzzles.com/?id=abc").is_err());
+        // The decoded data has an empty grid, which is reported rather than guessed at
+        assert!(import(&format!("https://sudokupad.app/fpuzzles{}", data)).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+use std::{fmt::Display, str::FromStr};
+
+use itertools::Itertools;
+
+use crate::{
+    chess_strategies::parse_digits,
+    grid::{parse_cell, Cell, Grid},
+    walkthrough::cell_name,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Fills in a 0-indexed digit
+    Place(usize),
+    /// Removes a 0-indexed candidate
+    Remove(usize),
+    /// Any other change, such as clearing a cell or adding candidates back
+    Set(Cell),
+}
+
+/// One change to one cell, with the cell as it was just before, to undo it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub cell: (usize, usize),
+    pub edit: Edit,
+    pub previous: Cell,
+}
+impl Change {
+    /// The changes turning `before` into `after`, cell by cell
+    pub fn diff(before: &Grid, after: &Grid) -> Vec<Change> {
+        let mut changes = vec![];
+        for (r, c) in (0..9).cartesian_product(0..9) {
+            let (mut previous, target) = (before[r][c], after[r][c]);
+            match (previous, target) {
+                _ if previous == target => {}
+                (_, Cell::Solved(n)) => changes.push(Change {
+                    cell: (r, c),
+                    edit: Edit::Place(n),
+                    previous,
+                }),
+                (Cell::Unsolved(old), Cell::Unsolved(new)) if (0..9).all(|n| old[n] || !new[n]) => {
+                    for n in (0..9).filter(|&n| old[n] && !new[n]) {
+                        changes.push(Change {
+                            cell: (r, c),
+                            edit: Edit::Remove(n),
+                            previous,
+                        });
+                        previous.remove_candidate(n);
+                    }
+                }
+                _ => changes.push(Change {
+                    cell: (r, c),
+                    edit: Edit::Set(target),
+                    previous,
+                }),
+            }
+        }
+        changes
+    }
+    fn redo(&self, grid: &mut Grid) {
+        let cell = &mut grid[self.cell.0][self.cell.1];
+        match self.edit {
+            Edit::Place(n) => *cell = Cell::Solved(n),
+            Edit::Remove(n) => {
+                cell.remove_candidate(n);
+            }
+            Edit::Set(new) => *cell = new,
+        }
+    }
+    fn undo(&self, grid: &mut Grid) {
+        grid[self.cell.0][self.cell.1] = self.previous;
+    }
+    /// Whether this change takes away the digit `solution` has in its cell
+    pub fn contradicts(&self, solution: &Grid) -> bool {
+        let Cell::Solved(digit) = solution[self.cell.0][self.cell.1] else {
+            return false;
+        };
+        match self.edit {
+            Edit::Place(n) | Edit::Set(Cell::Solved(n)) => n != digit,
+            Edit::Remove(n) => n == digit,
+            Edit::Set(Cell::Unsolved(candidates)) => !candidates[digit],
+        }
+    }
+}
+
+/// A cell as written in a journal file: a solved digit, or its candidates in brackets
+fn cell_text(cell: Cell) -> String {
+    match cell {
+        Cell::Solved(n) => (n + 1).to_string(),
+        Cell::Unsolved(candidates) => format!(
+            "[{}]",
+            (0..9).filter(|&n| candidates[n]).map(|n| n + 1).join("")
+        ),
+    }
+}
+fn parse_digit(s: &str) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(n @ 1..=9) => Ok(n - 1),
+        _ => Err(format!("invalid digit `{}`, expected 1-9", s.trim())),
+    }
+}
+fn parse_cell_text(s: &str) -> Result<Cell, String> {
+    match s.trim().strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Some(candidates) => Ok(Cell::Unsolved(parse_digits(candidates)?)),
+        None => Ok(Cell::Solved(parse_digit(s)?)),
+    }
+}
+impl FromStr for Change {
+    type Err = String;
+
+    /// Reads a change as written in a journal file, e.g. `r1c2: removed 5, was [1589]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid change `{}`", s.trim());
+        let (cell, rest) = s.split_once(':').ok_or_else(invalid)?;
+        let (edit, previous) = rest.rsplit_once(", was ").ok_or_else(invalid)?;
+        let edit = edit.trim();
+        let edit = if let Some(n) = edit.strip_prefix("placed ") {
+            Edit::Place(parse_digit(n)?)
+        } else if let Some(n) = edit.strip_prefix("removed ") {
+            Edit::Remove(parse_digit(n)?)
+        } else if let Some(candidates) = edit.strip_prefix("set to candidates") {
+            Edit::Set(Cell::Unsolved(parse_digits(candidates.trim())?))
+        } else if let Some(n) = edit.strip_prefix("set to ") {
+            Edit::Set(Cell::Solved(parse_digit(n)?))
+        } else {
+            return Err(invalid());
+        };
+        Ok(Change {
+            cell: parse_cell(cell)?,
+            edit,
+            previous: parse_cell_text(previous)?,
+        })
+    }
+}
+impl Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Edit::Place(n) => write!(f, "placed {}", n + 1),
+            Edit::Remove(n) => write!(f, "removed {}", n + 1),
+            Edit::Set(Cell::Solved(n)) => write!(f, "set to {}", n + 1),
+            Edit::Set(Cell::Unsolved(candidates)) => write!(
+                f,
+                "set to candidates {}",
+                (0..9).filter(|&n| candidates[n]).map(|n| n + 1).join("")
+            ),
+        }
+    }
+}
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", cell_name(self.cell), self.edit)
+    }
+}
+
+/// The changes made by one step of a solve, such as one strategy application
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// What made the changes, e.g. the strategy's name
+    pub label: String,
+    pub changes: Vec<Change>,
+}
+
+/// A record of every change made to a grid, step by step, so that the grid can be moved back
+/// and forth between steps
+#[derive(Clone, Debug, Default)]
+pub struct Journal {
+    entries: Vec<Entry>,
+    /// How many entries are applied to the grid; any after that have been undone
+    position: usize,
+}
+impl Journal {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+    pub fn position(&self) -> usize {
+        self.position
+    }
+    /// Records the step from `before` to `after`, dropping any undone steps. Steps which
+    /// change nothing aren't recorded
+    pub fn record(&mut self, label: impl Into<String>, before: &Grid, after: &Grid) {
+        let changes = Change::diff(before, after);
+        if changes.is_empty() {
+            return;
+        }
+        self.entries.truncate(self.position);
+        self.entries.push(Entry {
+            label: label.into(),
+            changes,
+        });
+        self.position += 1;
+    }
+    /// Reverts the last applied step on `grid`, returning it
+    pub fn undo(&mut self, grid: &mut Grid) -> Option<&Entry> {
+        self.position = self.position.checked_sub(1)?;
+        let entry = &self.entries[self.position];
+        for change in entry.changes.iter().rev() {
+            change.undo(grid);
+        }
+        Some(entry)
+    }
+    /// Applies the first undone step to `grid` again, returning it
+    pub fn redo(&mut self, grid: &mut Grid) -> Option<&Entry> {
+        let entry = self.entries.get(self.position)?;
+        for change in &entry.changes {
+            change.redo(grid);
+        }
+        self.position += 1;
+        Some(entry)
+    }
+    /// Undoes or redoes steps until just the first `position` are applied
+    pub fn rewind(&mut self, grid: &mut Grid, position: usize) -> Result<(), String> {
+        if position > self.entries.len() {
+            return Err(format!(
+                "there are only {} steps to go to",
+                self.entries.len()
+            ));
+        }
+        while self.position > position {
+            self.undo(grid);
+        }
+        while self.position < position {
+            self.redo(grid);
+        }
+        Ok(())
+    }
+    /// Every change made after the first `from` steps, up to the first `to`
+    pub fn diff(&self, from: usize, to: usize) -> impl Iterator<Item = &Change> {
+        self.entries[from.min(to)..to.min(self.entries.len())]
+            .iter()
+            .flat_map(|entry| &entry.changes)
+    }
+    /// Applies every undone step to `grid`, such as a fresh copy of the puzzle for a journal
+    /// read from a file. Fails at the first change whose cell isn't as it was when recorded
+    pub fn replay(&mut self, grid: &mut Grid) -> Result<(), String> {
+        while let Some(entry) = self.entries.get(self.position) {
+            for change in &entry.changes {
+                let found = grid[change.cell.0][change.cell.1];
+                if found != change.previous {
+                    return Err(format!(
+                        "step {} expects {} to be {}, but it is {}",
+                        self.position + 1,
+                        cell_name(change.cell),
+                        cell_text(change.previous),
+                        cell_text(found)
+                    ));
+                }
+                change.redo(grid);
+            }
+            self.position += 1;
+        }
+        Ok(())
+    }
+    /// The changes which take away a digit of `solution`, with the 1-indexed step making each
+    pub fn contradictions<'a>(
+        &'a self,
+        solution: &'a Grid,
+    ) -> impl Iterator<Item = (usize, &'a Entry, &'a Change)> {
+        self.entries.iter().enumerate().flat_map(move |(i, entry)| {
+            entry
+                .changes
+                .iter()
+                .filter(|change| change.contradicts(solution))
+                .map(move |change| (i + 1, entry, change))
+        })
+    }
+}
+/// Writes the journal as a file that [`Journal::from_str`] reads back: each step on a line
+/// of its own, followed by its changes indented
+impl Display for Journal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, entry) in self.entries.iter().enumerate() {
+            writeln!(f, "step {}: {}", i + 1, entry.label)?;
+            for change in &entry.changes {
+                writeln!(f, "  {}, was {}", change, cell_text(change.previous))?;
+            }
+        }
+        Ok(())
+    }
+}
+impl FromStr for Journal {
+    type Err = String;
+
+    /// Reads a journal file, with none of its steps applied yet
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut entries: Vec<Entry> = vec![];
+        for (i, line) in s.lines().enumerate() {
+            let in_line = |e: String| format!("line {}: {}", i + 1, e);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((_, label)) = line
+                .strip_prefix("step ")
+                .and_then(|rest| rest.split_once(": "))
+            {
+                entries.push(Entry {
+                    label: label.to_string(),
+                    changes: vec![],
+                });
+            } else {
+                let change = line.parse().map_err(in_line)?;
+                match entries.last_mut() {
+                    Some(entry) => entry.changes.push(change),
+                    None => return Err(in_line("a change before the first step".to_string())),
+                }
+            }
+        }
+        Ok(Journal {
+            entries,
+            position: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::Cell,
+        journal::{Edit, Journal},
+        parser::parse_grid,
+        puzzle::Puzzle,
+    };
+
+    #[test]
+    fn test_journal() {
+        let start = parse_grid(&".".repeat(81)).unwrap();
+        let mut grid = start.clone();
+        let mut journal = Journal::default();
+
+        let mut next = grid.clone();
+        next[0][0] = Cell::Solved(4);
+        next[0][1].remove_candidate(4);
+        next[0][1].remove_candidate(7);
+        journal.record("first", &grid, &next);
+        grid = next.clone();
+        next[0][0] = Cell::Unsolved([true; 9]);
+        journal.record("clear", &grid, &next);
+        grid = next;
+        journal.record("nothing", &grid, &grid.clone());
+        assert_eq!(journal.entries().len(), 2);
+
+        let changes = journal
+            .diff(0, 1)
+            .map(|change| change.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            ["r1c1: placed 5", "r1c2: removed 5", "r1c2: removed 8"]
+        );
+        assert_eq!(
+            journal.diff(1, 2).next().unwrap().edit,
+            Edit::Set(Cell::Unsolved([true; 9]))
+        );
+
+        assert_eq!(journal.undo(&mut grid).unwrap().label, "clear");
+        assert_eq!(grid[0][0], Cell::Solved(4));
+        journal.rewind(&mut grid, 0).unwrap();
+        assert_eq!(grid.to_pencil_marks(), start.to_pencil_marks());
+        assert!(journal.undo(&mut grid).is_none());
+        journal.rewind(&mut grid, 2).unwrap();
+        assert_eq!(grid[0][0], Cell::Unsolved([true; 9]));
+        assert!(journal.rewind(&mut grid, 3).is_err());
+
+        // Recording after undoing drops the undone steps
+        journal.undo(&mut grid);
+        let mut next = grid.clone();
+        next[8][8] = Cell::Solved(0);
+        journal.record("other", &grid, &next);
+        assert_eq!(journal.entries().len(), 2);
+        assert_eq!(journal.entries()[1].label, "other");
+        assert!(journal.redo(&mut next).is_none());
+    }
+
+    #[test]
+    fn test_replay() {
+        let classic =
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+        let start = parse_grid(classic).unwrap();
+        let solver = Puzzle {
+            grid: start.clone(),
+            constraints: vec![],
+        }
+        .solver();
+        let mut grid = start.clone();
+        let mut journal = Journal::default();
+        loop {
+            let before = grid.clone();
+            let Some(strategy) = solver.deduce(&mut grid) else {
+                break;
+            };
+            journal.record(strategy, &before, &grid);
+        }
+        assert!(grid.solved());
+        assert_eq!(journal.contradictions(&grid).count(), 0);
+
+        let text = journal.to_string();
+        assert!(text.starts_with("step 1: "));
+        let mut read: Journal = text.parse().unwrap();
+        assert_eq!(read.position(), 0);
+        assert_eq!(read.to_string(), text);
+        let mut replayed = start.clone();
+        read.replay(&mut replayed).unwrap();
+        assert_eq!(replayed.to_line(), grid.to_line());
+        read.rewind(&mut replayed, 0).unwrap();
+        assert_eq!(replayed.to_pencil_marks(), start.to_pencil_marks());
+
+        // Replaying onto a different grid stops at the first change that doesn't fit
+        let mut other = grid.clone();
+        let mut read: Journal = text.parse().unwrap();
+        assert!(read
+            .replay(&mut other)
+            .unwrap_err()
+            .starts_with("step 1 expects"));
+        assert!("step 1: x\n  r1c1: placed 0, was [1]"
+            .parse::<Journal>()
+            .is_err());
+
+        // An elimination of a digit the solution has is reported with its step
+        let mut wrong = Journal::default();
+        let mut next = start.clone();
+        next[0][2].remove_candidate(3);
+        wrong.record("bad", &start, &next);
+        let found = wrong.contradictions(&grid).collect::<Vec<_>>();
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            (found[0].0, found[0].2.to_string()),
+            (1, "r1c3: removed 4".to_string())
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use std::{collections::BTreeSet, str::FromStr};
+
+use log::trace;
+
+use crate::{
+    grid::{parse_cells, Grid},
+    sums::sum_options,
+};
+
+/// A path of cells, each one orthogonally or diagonally adjacent to the last
+#[derive(Clone, Debug)]
+pub struct Line(pub Vec<(usize, usize)>);
+impl FromStr for Line {
+    type Err = String;
+
+    /// Parses a comma-separated list of cells, e.g. `r1c1,r2c2,r2c3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cells = parse_cells(s)?;
+        if cells.len() < 2 {
+            return Err(format!("line `{}` needs at least two cells", s));
+        }
+        if let Some([a, b]) = cells
+            .windows(2)
+            .find(|w| w[0].0.abs_diff(w[1].0).max(w[0].1.abs_diff(w[1].1)) != 1)
+        {
+            return Err(format!(
+                "line `{}` jumps from r{}c{} to r{}c{}",
+                s,
+                a.0 + 1,
+                a.1 + 1,
+                b.0 + 1,
+                b.1 + 1
+            ));
+        }
+        Ok(Line(cells))
+    }
+}
+
+/// Keeps only the digits of each cell that some digit of the next or previous cell on the
+/// line can sit beside, given that digits `gap` or more apart are required
+fn whispers(grid: &mut Grid, lines: &[Line], gap: usize) -> bool {
+    let mut result = false;
+    for Line(cells) in lines {
+        for (i, (r, c)) in cells.iter().enumerate() {
+            let neighbours = [i.checked_sub(1), Some(i + 1)]
+                .into_iter()
+                .flatten()
+                .filter_map(|j| cells.get(j))
+                .map(|(rr, cc)| grid[*rr][*cc].options())
+                .collect::<Vec<_>>();
+            let allowed = std::array::from_fn(|n| {
+                neighbours
+                    .iter()
+                    .all(|opts| opts.iter().any(|m| n.abs_diff(*m) >= gap))
+            });
+            result |= grid[*r][*c].restrict(&allowed);
+        }
+    }
+    result
+}
+
+pub fn german_whispers(grid: &mut Grid, lines: &[Line]) -> bool {
+    trace!("Searching for german whispers");
+    whispers(grid, lines, 5)
+}
+
+pub fn dutch_whispers(grid: &mut Grid, lines: &[Line]) -> bool {
+    trace!("Searching for dutch whispers");
+    whispers(grid, lines, 4)
+}
+
+/// Whether each cell can be given a different one of `digits`, using only its own options
+fn can_fill(options: &[Vec<usize>], digits: &[usize]) -> bool {
+    fn augment(
+        cell: usize,
+        options: &[Vec<usize>],
+        digits: &[usize],
+        owner: &mut [Option<usize>],
+        visited: &mut [bool],
+    ) -> bool {
+        for (d, digit) in digits.iter().enumerate() {
+            if visited[d] || !options[cell].contains(digit) {
+                continue;
+            }
+            visited[d] = true;
+            if owner[d].is_none_or(|other| augment(other, options, digits, owner, visited)) {
+                owner[d] = Some(cell);
+                return true;
+            }
+        }
+        false
+    }
+
+    let mut owner = vec![None; digits.len()];
+    options.len() == digits.len()
+        && (0..options.len()).all(|cell| {
+            augment(
+                cell,
+                options,
+                digits,
+                &mut owner,
+                &mut vec![false; digits.len()],
+            )
+        })
+}
+
+pub fn renban(grid: &mut Grid, lines: &[Line]) -> bool {
+    trace!("Searching for renban lines");
+    let mut result = false;
+    for Line(cells) in lines {
+        let options = cells
+            .iter()
+            .map(|(r, c)| grid[*r][*c].options())
+            .collect::<Vec<_>>();
+        let mut allowed = vec![[false; 9]; cells.len()];
+        // Try every run of consecutive digits the line could hold
+        for start in 0..(10usize.saturating_sub(cells.len())) {
+            let run = (start..start + cells.len()).collect::<Vec<_>>();
+            for (i, opts) in options.iter().enumerate() {
+                for n in opts.iter().filter(|n| run.contains(n)) {
+                    let mut rest = options.clone();
+                    rest.remove(i);
+                    let others = run.iter().copied().filter(|m| m != n).collect::<Vec<_>>();
+                    if can_fill(&rest, &others) {
+                        allowed[i][*n] = true;
+                    }
+                }
+            }
+        }
+        for ((r, c), allowed) in cells.iter().zip(allowed.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+pub fn palindromes(grid: &mut Grid, lines: &[Line]) -> bool {
+    trace!("Searching for palindromes");
+    let mut result = false;
+    for Line(cells) in lines {
+        for (a, b) in cells.iter().zip(cells.iter().rev()).take(cells.len() / 2) {
+            let (a_opts, b_opts) = (grid[a.0][a.1].options(), grid[b.0][b.1].options());
+            let allowed = std::array::from_fn(|n| a_opts.contains(&n) && b_opts.contains(&n));
+            result |= grid[a.0][a.1].restrict(&allowed);
+            result |= grid[b.0][b.1].restrict(&allowed);
+        }
+    }
+    result
+}
+
+/// Narrows `targets` to the totals that every group of cells can reach, or `None` if a group
+/// is too unconstrained to search
+fn common_sums(
+    grid: &Grid,
+    groups: &[Vec<(usize, usize)>],
+    mut targets: BTreeSet<usize>,
+) -> Option<BTreeSet<usize>> {
+    for group in groups {
+        targets = sum_options(grid, group, &targets)?.sums;
+    }
+    Some(targets)
+}
+
+/// Restricts every group of cells to the digits that can make one of `targets`
+fn restrict_sums(
+    grid: &mut Grid,
+    groups: &[Vec<(usize, usize)>],
+    targets: &BTreeSet<usize>,
+) -> bool {
+    let mut result = false;
+    for group in groups {
+        let Some(options) = sum_options(grid, group, targets) else {
+            continue;
+        };
+        for ((r, c), allowed) in group.iter().zip(options.candidates.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+pub fn region_sums(grid: &mut Grid, lines: &[Line]) -> bool {
+    trace!("Searching for region sum lines");
+    let mut result = false;
+    for Line(cells) in lines {
+        let segments = cells
+            .chunk_by(|a, b| grid.region(*a) == grid.region(*b))
+            .map(|segment| segment.to_vec())
+            .collect::<Vec<_>>();
+        if segments.len() < 2 {
+            continue;
+        }
+        let Some(targets) = common_sums(grid, &segments, (1..=45).collect()) else {
+            continue;
+        };
+        result |= restrict_sums(grid, &segments, &targets);
+    }
+    result
+}
+
+pub fn zippers(grid: &mut Grid, lines: &[Line]) -> bool {
+    trace!("Searching for zipper lines");
+    let mut result = false;
+    for Line(cells) in lines {
+        let pairs = (0..cells.len() / 2)
+            .map(|i| vec![cells[i], cells[cells.len() - 1 - i]])
+            .collect::<Vec<_>>();
+        // An odd line has a center cell holding the sum, an even line's center pair shares it
+        let center = (cells.len() % 2 == 1).then(|| cells[cells.len() / 2]);
+        let targets = match center {
+            Some((r, c)) => grid[r][c].options().iter().map(|n| n + 1).collect(),
+            None => (3..=17).collect(),
+        };
+        let Some(targets) = common_sums(grid, &pairs, targets) else {
+            continue;
+        };
+        if let Some((r, c)) = center {
+            let allowed = std::array::from_fn(|n| targets.contains(&(n + 1)));
+            result |= grid[r][c].restrict(&allowed);
+        }
+        result |= restrict_sums(grid, &pairs, &targets);
+    }
+    result
+}
+
+pub fn between_lines(grid: &mut Grid, lines: &[Line]) -> bool {
+    trace!("Searching for between lines");
+    let mut result = false;
+    for Line(cells) in lines {
+        let (first, last) = (cells[0], cells[cells.len() - 1]);
+        let middle = cells[1..cells.len() - 1]
+            .iter()
+            .map(|(r, c)| grid[*r][*c].options())
+            .collect::<Vec<_>>();
+        let mut first_allowed = [false; 9];
+        let mut last_allowed = [false; 9];
+        let mut middle_allowed = vec![[false; 9]; middle.len()];
+        for a in grid[first.0][first.1].options() {
+            for b in grid[last.0][last.1].options() {
+                let (lo, hi) = (a.min(b), a.max(b));
+                let between = |n: &usize| lo < *n && *n < hi;
+                if a == b || !middle.iter().all(|opts| opts.iter().any(between)) {
+                    continue;
+                }
+                first_allowed[a] = true;
+                last_allowed[b] = true;
+                for (allowed, opts) in middle_allowed.iter_mut().zip(middle.iter()) {
+                    for n in opts.iter().filter(|n| between(n)) {
+                        allowed[*n] = true;
+                    }
+                }
+            }
+        }
+        result |= grid[first.0][first.1].restrict(&first_allowed);
+        result |= grid[last.0][last.1].restrict(&last_allowed);
+        for ((r, c), allowed) in cells[1..cells.len() - 1].iter().zip(middle_allowed.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+/// Digits strictly increase along a thermometer, starting from the bulb
+pub fn thermometers(grid: &mut Grid, lines: &[Line]) -> bool {
+    trace!("Searching for thermometers");
+    let mut result = false;
+    for Line(cells) in lines {
+        for pair in cells.windows(2) {
+            let [(lr, lc), (hr, hc)] = [pair[0], pair[1]];
+            let lowest = grid[lr][lc].options().into_iter().min().unwrap_or(9);
+            result |= grid[hr][hc].restrict(&std::array::from_fn(|n| n > lowest));
+        }
+        for pair in cells.windows(2).rev() {
+            let [(lr, lc), (hr, hc)] = [pair[0], pair[1]];
+            let highest = grid[hr][hc].options().into_iter().max().unwrap_or(0);
+            result |= grid[lr][lc].restrict(&std::array::from_fn(|n| n < highest));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::{Cell, Grid},
+        line_strategies::{
+            between_lines, dutch_whispers, german_whispers, palindromes, region_sums, renban,
+            thermometers, zippers, Line,
+        },
+    };
+
+    #[test]
+    fn test_german_whispers() {
+        let mut grid = Grid::default();
+        grid[0][1] = Cell::Unsolved([false, false, true, true, false, false, false, false, false]);
+        let line: Line = "r1c1,r1c2,r2c3".parse().unwrap();
+
+        assert!(german_whispers(&mut grid, &[line]));
+
+        assert_eq!(grid[0][0].candidates(), vec![7, 8]);
+        assert_eq!(grid[0][1].candidates(), vec![2, 3]);
+        assert_eq!(grid[1][2].candidates(), vec![7, 8]);
+    }
+
+    #[test]
+    fn test_dutch_whispers() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Solved(4);
+        let line: Line = "r1c1,r1c2,r1c3".parse().unwrap();
+
+        assert!(dutch_whispers(&mut grid, &[line]));
+
+        assert_eq!(grid[0][1].candidates(), vec![0, 8]);
+        // Every digit is at least four away from either 1 or 9
+        assert_eq!(grid[0][2], Cell::Unsolved([true; 9]));
+    }
+
+    #[test]
+    fn test_renban() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Solved(1);
+        grid[0][1] = Cell::Unsolved([false, false, true, false, false, false, false, false, false]);
+        let line: Line = "r1c1,r1c2,r2c2".parse().unwrap();
+
+        assert!(renban(&mut grid, &[line]));
+
+        // 2 and 3 can only be part of 1-2-3 or 2-3-4
+        assert_eq!(grid[1][1].candidates(), vec![0, 3]);
+    }
+
+    #[test]
+    fn test_palindromes() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Solved(4);
+        grid[2][2] = Cell::Unsolved([true, true, false, false, false, false, false, false, false]);
+        let line: Line = "r1c1,r2c1,r3c2,r3c3,r2c4".parse().unwrap();
+
+        assert!(palindromes(&mut grid, &[line]));
+
+        assert_eq!(grid[1][3].candidates(), vec![4]);
+        assert_eq!(grid[1][0].candidates(), vec![0, 1]);
+        assert_eq!(grid[2][1], Cell::Unsolved([true; 9]));
+    }
+
+    #[test]
+    fn test_region_sums() {
+        let mut grid = Grid::default();
+        grid[0][3] = Cell::Solved(2);
+        let line: Line = "r1c2,r1c3,r1c4".parse().unwrap();
+
+        assert!(region_sums(&mut grid, &[line]));
+
+        // Two different digits in box 1 must sum to 3
+        assert_eq!(grid[0][1].candidates(), vec![0, 1]);
+        assert_eq!(grid[0][2].candidates(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_zippers() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Solved(0);
+        grid[0][4] = Cell::Unsolved([false, false, false, false, false, true, false, false, true]);
+        let line: Line = "r1c1,r1c2,r1c3,r2c4,r1c5".parse().unwrap();
+
+        assert!(zippers(&mut grid, &[line]));
+
+        // 1 + r1c5 is r1c3, so r1c3 can only be 7, and r1c2 + r2c4 also make 7
+        assert_eq!(grid[0][2].candidates(), vec![6]);
+        assert_eq!(grid[0][4].candidates(), vec![5]);
+        assert_eq!(grid[0][1].candidates(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(grid[1][3].candidates(), vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_between_lines() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Solved(1);
+        grid[0][3] = Cell::Unsolved([false, false, false, false, false, true, false, false, true]);
+        let line: Line = "r1c1,r1c2,r1c3,r1c4".parse().unwrap();
+
+        assert!(between_lines(&mut grid, &[line]));
+
+        assert_eq!(grid[0][1].candidates(), vec![2, 3, 4, 5, 6, 7]);
+        assert_eq!(grid[0][2].candidates(), vec![2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_thermometers() {
+        let mut grid = Grid::default();
+        grid[0][3] = Cell::Unsolved([false, false, false, false, false, false, true, true, true]);
+        let lines: Vec<Line> = ["r1c1,r1c2,r1c3,r1c4"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+
+        assert!(thermometers(&mut grid, &lines));
+
+        // The tip is at most 9, so the bulb is at most 6, and each cell climbs by at least 1
+        assert_eq!(grid[0][0].candidates(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(grid[0][1].candidates(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(grid[0][2].candidates(), vec![2, 3, 4, 5, 6, 7]);
+        assert_eq!(grid[0][3].candidates(), vec![6, 7, 8]);
+    }
+}
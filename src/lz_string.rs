@@ -0,0 +1,118 @@
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Reads the compressed stream a bit at a time; each base64 character holds six bits, highest
+/// first, while values are assembled lowest bit first
+struct Bits<'a> {
+    input: &'a [u8],
+    index: usize,
+    value: u32,
+    position: u32,
+}
+impl Bits<'_> {
+    fn next_char(&mut self) -> u32 {
+        let value = self
+            .input
+            .get(self.index)
+            .and_then(|c| BASE64.iter().position(|b| b == c))
+            .unwrap_or(0) as u32;
+        self.index += 1;
+        value
+    }
+    fn read(&mut self, count: u32) -> u32 {
+        let mut bits = 0;
+        for power in 0..count {
+            if self.value & self.position != 0 {
+                bits |= 1 << power;
+            }
+            self.position >>= 1;
+            if self.position == 0 {
+                self.position = 32;
+                self.value = self.next_char();
+            }
+        }
+        bits
+    }
+}
+
+/// Decompresses a string made by lz-string's `compressToBase64`, the encoding used by f-puzzles
+/// and SudokuPad links
+pub fn decompress_from_base64(input: &str) -> Result<String, String> {
+    let input = input.trim().as_bytes();
+    let mut bits = Bits {
+        input,
+        index: 0,
+        value: 0,
+        position: 32,
+    };
+    bits.value = bits.next_char();
+
+    // Codes 0 and 1 introduce a new 8 or 16 bit character, and 2 ends the stream
+    let mut dictionary: Vec<Vec<u16>> = vec![vec![]; 3];
+    let mut num_bits = 3;
+    let mut enlarge_in = 4u32;
+    let first = match bits.read(2) {
+        0 => bits.read(8),
+        1 => bits.read(16),
+        _ => return Ok(String::new()),
+    };
+    let mut w = vec![first as u16];
+    dictionary.push(w.clone());
+    let mut result = w.clone();
+
+    loop {
+        if bits.index > input.len() {
+            return Err("compressed data ends unexpectedly".to_string());
+        }
+        let mut code = bits.read(num_bits) as usize;
+        match code {
+            0 | 1 => {
+                let c = bits.read(if code == 0 { 8 } else { 16 }) as u16;
+                dictionary.push(vec![c]);
+                code = dictionary.len() - 1;
+                enlarge_in -= 1;
+            }
+            2 => return String::from_utf16(&result).map_err(|e| e.to_string()),
+            _ => {}
+        }
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+
+        let entry = match dictionary.get(code) {
+            Some(entry) => entry.clone(),
+            None if code == dictionary.len() => [w.clone(), vec![w[0]]].concat(),
+            None => return Err("compressed data is corrupt".to_string()),
+        };
+        result.extend(&entry);
+        dictionary.push([w, vec![entry[0]]].concat());
+        enlarge_in -= 1;
+        w = entry;
+
+        if enlarge_in == 0 {
+            enlarge_in = 1 << num_bits;
+            num_bits += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::lz_string::decompress_from_base64;
+
+    #[test]
+    fn test_decompress() {
+        assert_eq!(decompress_from_base64("BYUwNmD2Q===").unwrap(), "hello");
+        assert_eq!(
+            decompress_from_base64("N4IgzglgXgpiBcBOANCA5gJwgEwQbQF0BfIA").unwrap(),
+            r#"{"size":9,"grid":[]}"#
+        );
+        // A repeated run refers to the dictionary entry it is itself creating
+        assert_eq!(decompress_from_base64("IY18ZlA=").unwrap(), "a".repeat(35));
+        assert_eq!(
+            decompress_from_base64("BYS4NmD2AEjAZNB3AbwJzAEyA===").unwrap(),
+            "héllo ☃ wörld"
+        );
+        assert!(decompress_from_base64("BYUwNmD2").is_err());
+    }
+}
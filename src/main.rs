@@ -1,114 +1,932 @@
-use std::fs::read_to_string;
+use std::{
+    fs::{read_to_string, File},
+    io::IsTerminal,
+    process::ExitCode,
+    time::Instant,
+};
 
-use clap::Parser;
-use log::{info, trace};
+use clap::{Parser, Subcommand};
+use log::{error, info, trace, warn, LevelFilter};
+use serde::Serialize;
 use simple_logger::{set_up_color_terminal, SimpleLogger};
 
 mod grid;
-use grid::{Cell, Grid};
+use grid::Grid;
 
 use crate::{
-    basic_sudoku::{
-        basic_elimination, hidden_singles, hidden_tuples, naked_singles, naked_tuples,
-        pointing_tuples,
-    },
-    chess_strategies::{kings, knights},
+    adjacency_strategies::parse_pair,
+    arrow_strategies::Arrow,
+    cage_strategies::Cage,
+    chess_strategies::parse_digits,
+    config::{parse_strategy, StrategyConfig},
+    generate::{Generated, Rng},
+    import::is_link,
+    journal::Journal,
+    line_strategies::Line,
+    multi_grid::{Layout, MultiGrid},
+    outside_strategies::{DiagonalClue, EdgeClue},
+    overlay_strategies::Overlay,
+    parser::check_givens,
+    puzzle::{Constraint, Puzzle},
+    repl::Repl,
+    report::{Canonical, Deduction, Format, Hint, Report, Status, Validation},
     solver::Solver,
+    svg::Highlight,
+    tui::App,
+    walkthrough::Step,
 };
 
+mod adjacency_strategies;
+mod arrow_strategies;
 mod basic_sudoku;
+mod batch;
+mod cage_strategies;
+mod canonical;
 mod chess_strategies;
+mod config;
+mod generate;
+mod houses;
+mod import;
+mod journal;
+mod line_strategies;
+mod lz_string;
+mod multi_grid;
+mod outside_strategies;
+mod overlay_strategies;
+mod parser;
+mod puzzle;
+mod rating;
+mod repl;
+mod report;
+#[cfg(feature = "serve")]
+mod server;
 mod solver;
+mod sums;
+mod svg;
+mod tui;
+mod walkthrough;
 
+/// Solves, explains, rates and generates sudoku puzzles, including many variants
 #[derive(Parser, Debug)]
-#[command()]
-struct Args {
-    /// File to read from.
+#[command(
+    after_help = "Exit codes: 0 solved or done, 1 file errors, 2 bad arguments, \
+    3 unsolvable, 4 multiple solutions, 5 invalid puzzle or strategy options, \
+    6 stuck without backtracking"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    #[command(flatten)]
+    log_level: clap_verbosity_flag::Verbosity,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Solves a puzzle logically, then by backtracking if enabled
+    Solve(SolveArgs),
+    /// Shows the next logical step
+    Hint(PuzzleArgs),
+    /// Rates a puzzle by the strategies it needs
+    Rate(PuzzleArgs),
+    /// Generates a random puzzle with a unique solution
+    Generate(GenerateArgs),
+    /// Solves a puzzle by hand in an interactive terminal UI, with hints, undo and checking
+    Play(PuzzleArgs),
+    /// Steps through a solve one command at a time, placing digits and eliminating candidates
+    /// by hand, to explore a puzzle or debug a strategy
+    Repl(ReplArgs),
+    /// Checks that a puzzle is well formed and has exactly one solution
+    Validate(PuzzleArgs),
+    /// Prints a classic puzzle in a canonical form
+    ///
+    /// The form is shared by every puzzle which differs from it only by relabelling digits,
+    /// reordering rows and columns or transposing
+    Canonicalize(PuzzleArgs),
+    /// Solves a file of puzzles and prints timing statistics
+    ///
+    /// Reads one plain puzzle per line, always backtracking, and prints a line per puzzle
+    /// followed by summary statistics
+    Bench(BenchArgs),
+    /// Serves a JSON API on localhost, for `POST /solve`, `/hint`, `/rate`, `/generate` and
+    /// `/validate`
+    ///
+    /// Each request's body is a puzzle as for `solve`, either a JSON puzzle file or a plain
+    /// grid, and the response is the JSON `--format json` would print. `/solve` takes
+    /// `?backtracking=false` and `/generate` takes `?seed=N` and an optional template
+    #[cfg(feature = "serve")]
+    Serve(ServeArgs),
+}
+
+/// Options shared by the subcommands which read a single puzzle
+#[derive(clap::Args, Debug)]
+struct PuzzleArgs {
+    /// File to read from: either a plain grid, a grid of pencil marks as written by --save, or a
+    /// .toml or .json puzzle file giving the givens, regions, pencil marks and constraints.
+    /// An f-puzzles or SudokuPad link may be given instead of a file.
     /// If omitted, the sudoku will be read from stdin
     input: Option<String>,
+    /// How to print the result: `text`, or `json` for a single JSON document on stdout
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    format: Format,
+    #[command(flatten)]
+    constraints: ConstraintArgs,
+    #[command(flatten)]
+    strategies: StrategyArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct SolveArgs {
+    #[command(flatten)]
+    puzzle: PuzzleArgs,
+    /// Solves several overlapping grids (samurai, butterfly or twodoku), given one per line.
+    /// Variant constraints apply to every grid alike
+    #[arg(long, value_name = "LAYOUT", conflicts_with_all = ["format", "save", "svg", "walkthrough", "journal"])]
+    layout: Option<Layout>,
     /// Enables backtracking when no logical steps remain
     #[arg(short, long)]
     backtracking: bool,
+    /// Writes the grid's pencil marks to FILE once no logical steps remain, so the solve can be
+    /// resumed from there later. Cells down to one candidate are written as plain digits and
+    /// so read back as solved
+    #[arg(long, value_name = "FILE")]
+    save: Option<String>,
+    /// Draws the grid as an SVG image to FILE once solving stops, with any clues and the
+    /// remaining pencil marks
+    #[arg(long, value_name = "FILE")]
+    svg: Option<String>,
+    /// Draws the grid just before the Nth deduction instead, highlighting what it places and
+    /// eliminates
+    #[arg(long, value_name = "N", requires = "svg")]
+    svg_step: Option<usize>,
+    /// Writes a step-by-step explanation of the logical solve to FILE, as HTML if its name ends
+    /// in .html and as Markdown otherwise
+    #[arg(long, value_name = "FILE")]
+    walkthrough: Option<String>,
+    /// Writes every placement and elimination of the logical solve to FILE, step by step, for
+    /// the REPL's `replay`. If the puzzle is solved, warns about any that removed a digit of
+    /// the solution
+    #[arg(long, value_name = "FILE")]
+    journal: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct GenerateArgs {
+    /// Puzzle file to start from, for its regions, constraints and any givens
+    #[arg(long, value_name = "FILE")]
+    template: Option<String>,
+    /// Seed for the random choices, to generate the same puzzle again. Defaults to the time
+    #[arg(long)]
+    seed: Option<u64>,
+    /// How to print the result: `text`, or `json` for a single JSON document on stdout
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    format: Format,
+    #[command(flatten)]
+    constraints: ConstraintArgs,
+    #[command(flatten)]
+    strategies: StrategyArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct ReplArgs {
+    /// Puzzle to start with, as for `solve`. If omitted, starts with an empty grid; use
+    /// `load` to load another
+    input: Option<String>,
+    #[command(flatten)]
+    constraints: ConstraintArgs,
+    #[command(flatten)]
+    strategies: StrategyArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct BenchArgs {
+    /// File with one plain puzzle per line, skipping blank lines and `#` comments.
+    /// If omitted, the puzzles will be read from stdin
+    input: Option<String>,
+    /// File to write results to, instead of stdout
+    #[arg(long, value_name = "FILE")]
+    output: Option<String>,
+    #[command(flatten)]
+    constraints: ConstraintArgs,
+    #[command(flatten)]
+    strategies: StrategyArgs,
+}
+
+#[cfg(feature = "serve")]
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// Port to listen on, on 127.0.0.1 only
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+    /// Seconds a request may take before backtracking is stopped and it fails
+    #[arg(long, value_name = "SECONDS", default_value = "10", value_parser = server::parse_timeout)]
+    timeout: std::time::Duration,
+    /// How many requests are worked on at once; others wait their turn. Defaults to the
+    /// number of CPUs
+    #[arg(long, value_name = "N")]
+    workers: Option<std::num::NonZeroUsize>,
+    /// Lets web pages from this origin call the API, e.g. `http://localhost:3000`
+    #[arg(long, value_name = "ORIGIN")]
+    allow_origin: Option<String>,
+    #[command(flatten)]
+    strategies: StrategyArgs,
+}
+
+/// Choice of the strategies used for logical steps
+#[derive(clap::Args, Debug)]
+struct StrategyArgs {
+    /// Uses only these strategies, in this order, e.g. `singles,basic-elimination,tuples`.
+    /// `singles` and `tuples` stand for every strategy of that kind
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', value_parser = parse_strategy)]
+    strategies: Option<Vec<String>>,
+    /// Leaves out these strategies
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', value_parser = parse_strategy)]
+    disable: Vec<String>,
+    /// Leaves out every strategy harder than this one, as rated by `rate`. Strategies are
+    /// ranked in tiers, not one by one: easy (singles and basic elimination), medium (pointing
+    /// tuples), and hard (naked and hidden tuples, and every variant strategy). So
+    /// `naked-tuples` and `kropki` both allow everything
+    #[arg(long, value_name = "NAME", value_parser = parse_strategy)]
+    max_technique: Option<String>,
+    /// TOML file with `strategies`, `disable` and `max-technique` keys like the flags above.
+    /// Flags take precedence, and add to the disabled strategies
+    #[arg(long, value_name = "FILE")]
+    config: Option<String>,
+}
+impl StrategyArgs {
+    fn config(&self) -> Result<StrategyConfig, String> {
+        let mut config = match &self.config {
+            Some(path) => {
+                let text = read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                StrategyConfig::from_toml(&text).map_err(|e| format!("{}: {}", path, e))?
+            }
+            None => StrategyConfig::default(),
+        };
+        if let Some(strategies) = &self.strategies {
+            config.strategies = Some(strategies.clone());
+        }
+        config.disable.extend(self.disable.iter().cloned());
+        if let Some(max_technique) = &self.max_technique {
+            config.max_technique = Some(max_technique.clone());
+        }
+        Ok(config)
+    }
+}
+
+/// Variant rules, given as flags
+#[derive(clap::Args, Debug)]
+struct ConstraintArgs {
     /// Enables antiKing constraint
     #[arg(short = 'k', long)]
     antiking: bool,
     /// Enables antikNight constraint
     #[arg(short = 'n', long)]
     antiknight: bool,
-    #[command(flatten)]
-    log_level: clap_verbosity_flag::Verbosity,
+    /// Enables antiQueen constraint, optionally only for the given digits (e.g. --antiqueen 9)
+    #[arg(long, value_name = "DIGITS", value_parser = parse_digits, num_args = 0..=1, default_missing_value = "123456789")]
+    antiqueen: Option<[bool; 9]>,
+    /// Enables antiBishop constraint, optionally only for the given digits
+    #[arg(long, value_name = "DIGITS", value_parser = parse_digits, num_args = 0..=1, default_missing_value = "123456789")]
+    antibishop: Option<[bool; 9]>,
+    /// Enables antiRook constraint along the two long diagonals, so digits can't repeat on
+    /// them, optionally only for the given digits
+    #[arg(long, value_name = "DIGITS", value_parser = parse_digits, num_args = 0..=1, default_missing_value = "123456789")]
+    antirook_diagonal: Option<[bool; 9]>,
+    /// Adds an arrow, given as CIRCLE=SHAFT cell lists (e.g. r1c1=r1c2,r1c3).
+    /// A two-cell circle is read as a two-digit pill
+    #[arg(long = "arrow", value_name = "CIRCLE=SHAFT")]
+    arrows: Vec<Arrow>,
+    /// Adds a killer cage, given as its cells and an optional sum (e.g. r1c1,r1c2=10)
+    #[arg(long = "cage", value_name = "CELLS[=SUM]")]
+    cages: Vec<Cage>,
+    /// Adds a thermometer, given as a path of cells starting from the bulb
+    #[arg(long = "thermo", value_name = "LINE")]
+    thermos: Vec<Line>,
+    /// Adds a white kropki dot (consecutive digits) between two adjacent cells (e.g. r1c1,r1c2)
+    #[arg(long = "white-dot", value_name = "CELLS", value_parser = parse_pair)]
+    white_dots: Vec<[(usize, usize); 2]>,
+    /// Adds a black kropki dot (one digit double the other) between two adjacent cells
+    #[arg(long = "black-dot", value_name = "CELLS", value_parser = parse_pair)]
+    black_dots: Vec<[(usize, usize); 2]>,
+    /// Enables the kropki negative constraint: every possible dot is given
+    #[arg(long)]
+    all_dots: bool,
+    /// Adds an X (digits sum to 10) between two adjacent cells (e.g. r1c1,r1c2)
+    #[arg(long = "x-pair", value_name = "CELLS", value_parser = parse_pair)]
+    x_pairs: Vec<[(usize, usize); 2]>,
+    /// Adds a V (digits sum to 5) between two adjacent cells
+    #[arg(long = "v-pair", value_name = "CELLS", value_parser = parse_pair)]
+    v_pairs: Vec<[(usize, usize); 2]>,
+    /// Enables the XV negative constraint: every possible X and V is given
+    #[arg(long)]
+    all_xv: bool,
+    /// Enables the non-consecutive constraint
+    #[arg(long)]
+    nonconsecutive: bool,
+    /// Adds a German whispers line (adjacent digits differ by at least 5), given as a
+    /// comma-separated path of cells (e.g. r1c1,r2c2,r2c3)
+    #[arg(long = "german-whispers", value_name = "LINE")]
+    german_whispers: Vec<Line>,
+    /// Adds a Dutch whispers line (adjacent digits differ by at least 4)
+    #[arg(long = "dutch-whispers", value_name = "LINE")]
+    dutch_whispers: Vec<Line>,
+    /// Adds a renban line (a set of consecutive digits in any order)
+    #[arg(long = "renban", value_name = "LINE")]
+    renbans: Vec<Line>,
+    /// Adds a palindrome line (reads the same in both directions)
+    #[arg(long = "palindrome", value_name = "LINE")]
+    palindromes: Vec<Line>,
+    /// Adds a region sum line (equal sums in every box the line passes through)
+    #[arg(long = "region-sum", value_name = "LINE")]
+    region_sums: Vec<Line>,
+    /// Adds a zipper line (cells equidistant from the center sum to the center value)
+    #[arg(long = "zipper", value_name = "LINE")]
+    zippers: Vec<Line>,
+    /// Adds a between line (digits lie strictly between the two end circles)
+    #[arg(long = "between", value_name = "LINE")]
+    between_lines: Vec<Line>,
+    /// Adds a little killer clue: the sum along a diagonal, given as an outside position,
+    /// direction and sum (e.g. r0c3:se=15 for a clue above column 3 pointing down-right)
+    #[arg(long = "little-killer", value_name = "POSITION:DIRECTION=SUM")]
+    little_killers: Vec<DiagonalClue>,
+    /// Adds an X-sums clue: the first X digits from this side sum to the clue, where X is the
+    /// first digit (e.g. r0c3=15 above column 3, r5c10=20 right of row 5)
+    #[arg(long = "x-sum", value_name = "POSITION=SUM")]
+    x_sums: Vec<EdgeClue>,
+    /// Adds a skyscraper clue: how many digits are visible from this side, with taller digits
+    /// hiding shorter ones behind them (e.g. r0c3=4)
+    #[arg(long = "skyscraper", value_name = "POSITION=COUNT")]
+    skyscrapers: Vec<EdgeClue>,
+    /// Adds an overlay: even:CELLS, odd:CELLS, gt:A,B (A > B), lt:A,B (A < B), or quad:CELL=DIGITS
+    /// for a quadruple on the point below and right of CELL (e.g. quad:r1c1=127)
+    #[arg(long = "overlay", value_name = "KIND:SPEC")]
+    overlays: Vec<Overlay>,
+    /// Adds both main diagonals as houses (Sudoku-X)
+    #[arg(short = 'x', long)]
+    diagonal: bool,
+    /// Adds the four extra Windoku boxes as houses
+    #[arg(long)]
+    windoku: bool,
+    /// Adds disjoint groups (the same position in every box) as houses
+    #[arg(long)]
+    disjoint_groups: bool,
+}
+
+impl ConstraintArgs {
+    /// The variant rules enabled by flags
+    fn constraints(&self) -> Vec<Constraint> {
+        let flags = [
+            (self.antiking, Constraint::Antiking),
+            (self.antiknight, Constraint::Antiknight),
+            (self.nonconsecutive, Constraint::Nonconsecutive),
+            (self.all_dots, Constraint::AllDots),
+            (self.all_xv, Constraint::AllXv),
+            (self.diagonal, Constraint::Diagonal),
+            (self.windoku, Constraint::Windoku),
+            (self.disjoint_groups, Constraint::DisjointGroups),
+        ];
+        let mut constraints = flags
+            .into_iter()
+            .filter_map(|(enabled, constraint)| enabled.then_some(constraint))
+            .collect::<Vec<_>>();
+        if let Some(digits) = self.antiqueen {
+            constraints.push(Constraint::Antiqueen { digits });
+        }
+        if let Some(digits) = self.antibishop {
+            constraints.push(Constraint::Antibishop { digits });
+        }
+        if let Some(digits) = self.antirook_diagonal {
+            constraints.push(Constraint::AntirookDiagonal { digits });
+        }
+        // Every clue flag holds a list of specs, each becoming one constraint
+        macro_rules! clues {
+            ($($field:ident => $variant:ident),* $(,)?) => {
+                $(constraints.extend(
+                    self.$field.iter().map(|spec| Constraint::$variant { spec: spec.clone() }),
+                );)*
+            };
+        }
+        clues!(
+            cages => Cage,
+            thermos => Thermo,
+            arrows => Arrow,
+            german_whispers => GermanWhispers,
+            dutch_whispers => DutchWhispers,
+            renbans => Renban,
+            palindromes => Palindrome,
+            region_sums => RegionSum,
+            zippers => Zipper,
+            between_lines => Between,
+            little_killers => LittleKiller,
+            x_sums => XSum,
+            skyscrapers => Skyscraper,
+            overlays => Overlay,
+            white_dots => WhiteDot,
+            black_dots => BlackDot,
+            x_pairs => XPair,
+            v_pairs => VPair,
+        );
+        constraints
+    }
+}
+
+/// Process exit codes. Clap exits with 2 when the command line itself is wrong
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Exit {
+    /// Solved, or the command otherwise succeeded
+    Success = 0,
+    /// Files couldn't be read or written
+    Failure = 1,
+    Unsolvable = 3,
+    Multiple = 4,
+    /// The puzzle couldn't be read, its givens conflict, or the strategy options are invalid
+    Invalid = 5,
+    /// No logical steps remain and backtracking is disabled
+    Stuck = 6,
+}
+impl From<Status> for Exit {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Solved => Exit::Success,
+            Status::Stuck => Exit::Stuck,
+            Status::Unsolvable => Exit::Unsolvable,
+            Status::Multiple => Exit::Multiple,
+        }
+    }
 }
 
-fn main() -> Result<(), ()> {
-    let args = Args::parse();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
 
     set_up_color_terminal();
     let logger = SimpleLogger::new();
 
-    if let Err(_) = log::set_boxed_logger(Box::new(logger)) {
-        println!("Failed to initialize logging");
-        return Err(());
+    if log::set_boxed_logger(Box::new(logger)).is_err() {
+        eprintln!("Failed to initialize logging");
+        return ExitCode::from(Exit::Failure as u8);
     }
-    log::set_max_level(args.log_level.log_level_filter());
+    log::set_max_level(cli.log_level.log_level_filter());
 
-    let Ok(input) = (match args.input {
+    let result = match &cli.command {
+        Command::Solve(args) => solve(args),
+        Command::Hint(args) => hint(args),
+        Command::Rate(args) => rate(args),
+        Command::Generate(args) => generate(args),
+        Command::Play(args) => play(args),
+        Command::Repl(args) => repl(args),
+        Command::Validate(args) => validate(args),
+        Command::Canonicalize(args) => canonicalize(args),
+        Command::Bench(args) => bench(args),
+        #[cfg(feature = "serve")]
+        Command::Serve(args) => serve(args),
+    };
+    let (Ok(exit) | Err(exit)) = result;
+    ExitCode::from(exit as u8)
+}
+
+/// Reads the input file, or stdin when there is none. A link is returned as it is
+fn read_input(input: Option<&str>, one_per_line: bool) -> Result<String, Exit> {
+    let result = match input {
+        Some(link) if is_link(link) => Ok(link.to_string()),
         Some(infile) => read_to_string(infile),
-        _ => {
+        None if one_per_line => {
+            eprintln!("Enter each puzzle on its own line, then end the input.");
+            std::io::read_to_string(std::io::stdin())
+        }
+        None => {
             let mut out = String::new();
-            println!("Enter your puzzle in one line, using any non-digit, non-whitespace character to represent an unknown cell.");
+            eprintln!("Enter your puzzle in one line, using `.` or `0` for an unknown cell.");
             std::io::stdin().read_line(&mut out).map(|_| out)
         }
-    }) else {
-        return Err(());
     };
+    result.map_err(|e| {
+        error!("Failed to read the puzzle: {}", e);
+        Exit::Failure
+    })
+}
+
+/// Reads a puzzle from a link, a .toml or .json puzzle file, or a plain or pencil-mark grid
+fn read_puzzle(input: Option<&str>) -> Result<Puzzle, Exit> {
+    let text = read_input(input, false)?;
+    Puzzle::parse(input, &text).map_err(|e| {
+        error!("{}", e);
+        Exit::Invalid
+    })
+}
+
+/// Adds the constraints given as flags, and the houses of all constraints to the grid
+fn with_constraints(puzzle: Puzzle, constraints: &ConstraintArgs) -> Result<Puzzle, Exit> {
+    let puzzle = puzzle
+        .with_constraints(constraints.constraints())
+        .map_err(|e| {
+            error!("{}", e);
+            Exit::Invalid
+        })?;
+    trace!("initial grid: \n{}", puzzle.grid);
+    Ok(puzzle)
+}
+
+fn load(args: &PuzzleArgs) -> Result<Puzzle, Exit> {
+    with_constraints(read_puzzle(args.input.as_deref())?, &args.constraints)
+}
+
+/// Builds the puzzle's solver, restricted to the chosen strategies
+fn solver(puzzle: &Puzzle, strategies: &StrategyArgs) -> Result<Solver, Exit> {
+    let mut solver = puzzle.solver();
+    strategies
+        .config()
+        .and_then(|config| config.apply(&mut solver))
+        .map_err(|e| {
+            error!("Invalid strategy config: {}", e);
+            Exit::Invalid
+        })?;
+    Ok(solver)
+}
+
+fn write_file(path: &str, contents: String, what: &str) -> Result<(), Exit> {
+    std::fs::write(path, contents).map_err(|e| {
+        error!("Failed to write {}: {}", what, e);
+        Exit::Failure
+    })
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), Exit> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| {
+        error!("Failed to write the report: {}", e);
+        Exit::Failure
+    })?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn solve(args: &SolveArgs) -> Result<Exit, Exit> {
+    if let Some(layout) = args.layout {
+        return solve_multi(args, layout);
+    }
+    let puzzle = load(&args.puzzle)?;
+    let solver = solver(&puzzle, &args.puzzle.strategies)?;
+    let mut grid = puzzle.grid.clone();
 
-    let mut grid = Grid([[Cell::default(); 9]; 9]);
-    for (i, char) in input.replace([' ', '\n', '\t'], "").chars().enumerate() {
-        if i >= 81 {
+    let givens = grid.clone();
+    let start = Instant::now();
+    let mut deductions = vec![];
+    let mut step = None;
+    let mut steps = vec![];
+    let mut journal = Journal::default();
+    loop {
+        let before = grid.clone();
+        let Some(strategy) = solver.deduce(&mut grid) else {
             break;
+        };
+        trace!("{}", grid);
+        journal.record(strategy, &before, &grid);
+        deductions.push(Deduction::new(strategy, &before, &grid));
+        if args.svg_step == Some(deductions.len()) {
+            step = Some((
+                Highlight::from_step(strategy, &before, &grid),
+                before.clone(),
+            ));
         }
-        grid[i / 9][i % 9] = char.to_digit(10).map(|d| d as usize).into();
+        if args.walkthrough.is_some() {
+            steps.push(Step::new(strategy, &before, &grid));
+        }
+    }
+    let logic_time = start.elapsed();
+    if let Some(path) = &args.save {
+        write_file(path, grid.to_pencil_marks(), "the grid")?;
     }
 
-    trace!("initial grid: \n{}", grid);
+    if let Some(path) = &args.walkthrough {
+        let walkthrough = if path.ends_with(".html") {
+            walkthrough::to_html(&givens, &steps, &puzzle.constraints)
+        } else {
+            walkthrough::to_markdown(&givens, &steps)
+        };
+        write_file(path, walkthrough, "the walkthrough")?;
+    }
 
-    let mut solver = Solver::new();
-    solver.add_strategy(naked_singles);
-    solver.add_strategy(basic_elimination);
-    if args.antiking {
-        solver.add_strategy(kings);
+    if !grid.solved() || grid.broken() {
+        info!(
+            "Failed to find a solution logically.\n{}",
+            grid.to_pencil_marks()
+        );
     }
-    if args.antiknight {
-        solver.add_strategy(knights)
+    let (status, backtracking) = report::finish(&solver, &mut grid, args.backtracking);
+    match status {
+        Status::Solved if backtracking.is_some() => info!("Solved!\n{}", grid),
+        Status::Solved => info!("Puzzle solved!"),
+        Status::Stuck => {
+            info!("Run with --backtracking to try again with backtracking enabled")
+        }
+        Status::Unsolvable => info!("Puzzle has no solutions"),
+        Status::Multiple => info!("Puzzle has more than one solution"),
     }
-    solver.add_strategy(hidden_singles);
-    solver.add_strategy(naked_tuples);
-    solver.add_strategy(hidden_tuples);
-    solver.add_strategy(pointing_tuples);
 
-    let mut failed = false;
-    while !grid.solved() && !failed {
-        if solver.step(&mut grid) {
-            trace!("{}", grid);
-        } else {
-            failed = true;
+    if let Some(path) = &args.journal {
+        write_file(path, journal.to_string(), "the journal")?;
+        if status == Status::Solved {
+            for (step, entry, change) in journal.contradictions(&grid) {
+                warn!(
+                    "Step {} ({}) is wrong: {}, but the solution has {} there",
+                    step, entry.label, change, grid[change.cell.0][change.cell.1]
+                );
+            }
         }
     }
-    if !failed {
-        info!("Puzzle solved!");
-        return Ok(());
+
+    if let Some(path) = &args.svg {
+        let svg = match (&step, args.svg_step) {
+            (Some((highlight, before)), _) => {
+                svg::render(before, &givens, &puzzle.constraints, Some(highlight))
+            }
+            (None, Some(n)) => {
+                error!("There is no deduction {}, only {}", n, deductions.len());
+                return Err(Exit::Failure);
+            }
+            (None, None) => svg::render(&grid, &givens, &puzzle.constraints, None),
+        };
+        write_file(path, svg, "the SVG")?;
     }
 
-    info!("Failed to find a solution logically.");
-    if args.backtracking {
-        trace!("Starting backtracking");
-        if solver.backtrack(&mut grid) {
-            info!("Solved!")
-        } else {
-            info!("Puzzle has no solutions")
+    match args.puzzle.format {
+        Format::Text => print!("{}", grid),
+        Format::Json => {
+            let mut report =
+                Report::new(status, &grid, deductions).with_timing(logic_time, start.elapsed());
+            report.backtracking = backtracking;
+            print_json(&report)?;
+        }
+    }
+    Ok(status.into())
+}
+
+fn solve_multi(args: &SolveArgs, layout: Layout) -> Result<Exit, Exit> {
+    if args
+        .puzzle
+        .input
+        .as_deref()
+        .is_some_and(|input| is_link(input) || input.ends_with(".toml") || input.ends_with(".json"))
+    {
+        error!("Puzzle files can't be combined with --layout");
+        return Err(Exit::Invalid);
+    }
+    let input = read_input(args.puzzle.input.as_deref(), true)?;
+    let mut multi = MultiGrid::parse(layout, &input).map_err(|e| {
+        error!("{}", e);
+        Exit::Invalid
+    })?;
+    let puzzle = Puzzle {
+        grid: Grid::default(),
+        constraints: args.puzzle.constraints.constraints(),
+    };
+    let extra_houses = puzzle.extra_houses();
+    for (i, grid) in multi.grids.iter_mut().enumerate() {
+        grid.extra_houses.extend(extra_houses.iter().copied());
+        if let Err(e) = check_givens(grid) {
+            error!("Invalid puzzle: grid {}: {}", i + 1, e);
+            return Err(Exit::Invalid);
         }
+    }
+    let solver = solver(&puzzle, &args.puzzle.strategies)?;
+
+    while multi.step(&solver) {
+        trace!("{}", multi);
+    }
+    let status = if multi.solved() && !multi.broken() {
+        info!("Puzzle solved!");
+        Status::Solved
     } else {
-        info!("Run with --backtracking to try again with backtracking enabled");
+        info!("Failed to find a solution logically.");
+        if !args.backtracking {
+            info!("Run with --backtracking to try again with backtracking enabled");
+            Status::Stuck
+        } else if multi.backtrack(&solver) {
+            info!("Solved!");
+            Status::Solved
+        } else {
+            info!("Puzzle has no solutions");
+            Status::Unsolvable
+        }
+    };
+    print!("{}", multi);
+    Ok(status.into())
+}
+
+fn hint(args: &PuzzleArgs) -> Result<Exit, Exit> {
+    let puzzle = load(args)?;
+    let solver = solver(&puzzle, &args.strategies)?;
+    let mut grid = puzzle.grid;
+    let before = grid.clone();
+    let hint = solver
+        .deduce(&mut grid)
+        .map(|strategy| Hint::new(strategy, &before, &grid));
+    match (args.format, &hint) {
+        (Format::Json, _) => print_json(&hint)?,
+        (Format::Text, Some(hint)) => println!("{}", hint.explanation),
+        (Format::Text, None) if before.solved() => println!("The puzzle is already solved"),
+        (Format::Text, None) => println!("No logical steps remain"),
+    }
+    Ok(match hint {
+        None if !before.solved() => Exit::Stuck,
+        _ => Exit::Success,
+    })
+}
+
+fn rate(args: &PuzzleArgs) -> Result<Exit, Exit> {
+    let puzzle = load(args)?;
+    let rating = rating::rate(&solver(&puzzle, &args.strategies)?, &puzzle.grid);
+    match args.format {
+        Format::Text => print!("{}", rating),
+        Format::Json => print_json(&rating)?,
+    }
+    Ok(rating.status.into())
+}
+
+fn generate(args: &GenerateArgs) -> Result<Exit, Exit> {
+    let template = match &args.template {
+        Some(path) => read_puzzle(Some(path))?,
+        None => Puzzle {
+            grid: Grid::default(),
+            constraints: vec![],
+        },
+    };
+    let puzzle = with_constraints(template, &args.constraints)?;
+    let seed = args.seed.unwrap_or_else(Rng::time_seed);
+    info!("Generating with seed {}", seed);
+
+    let solver = solver(&puzzle, &args.strategies)?;
+    let Some((grid, solution)) = generate::generate(&solver, &puzzle.grid, &mut Rng::new(seed))
+    else {
+        error!("The template has no solutions");
+        return Err(Exit::Unsolvable);
+    };
+    let generated = Generated {
+        puzzle: grid.to_line(),
+        solution: solution.to_line(),
+        seed,
+    };
+    match args.format {
+        Format::Text => println!("{}", generated.puzzle),
+        Format::Json => print_json(&generated)?,
+    }
+    Ok(Exit::Success)
+}
+
+fn play(args: &PuzzleArgs) -> Result<Exit, Exit> {
+    if !std::io::stdout().is_terminal() {
+        error!("play needs a terminal to draw in");
+        return Err(Exit::Failure);
+    }
+    let puzzle = load(args)?;
+    let solver = solver(&puzzle, &args.strategies)?;
+    let mut app = App::new(&solver, puzzle.grid, &puzzle.constraints);
+
+    // Log lines would be drawn over the grid
+    let level = log::max_level();
+    log::set_max_level(LevelFilter::Off);
+    let result = tui::run(&mut app);
+    log::set_max_level(level);
+    result.map_err(|e| {
+        error!("Terminal error: {}", e);
+        Exit::Failure
+    })?;
+    Ok(Exit::Success)
+}
+
+fn repl(args: &ReplArgs) -> Result<Exit, Exit> {
+    let puzzle = match &args.input {
+        Some(input) => read_puzzle(Some(input))?,
+        None => Puzzle {
+            grid: Grid::default(),
+            constraints: vec![],
+        },
+    };
+    let config = args.strategies.config().map_err(|e| {
+        error!("Invalid strategy config: {}", e);
+        Exit::Invalid
+    })?;
+    let mut repl = Repl::new(puzzle, args.constraints.constraints(), config).map_err(|e| {
+        error!("{}", e);
+        Exit::Invalid
+    })?;
+
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        eprintln!("Type `help` for a list of commands.");
+    }
+    repl.run(
+        std::io::stdin().lock(),
+        &mut std::io::stdout().lock(),
+        interactive,
+    )
+    .map_err(|e| {
+        error!("{}", e);
+        Exit::Failure
+    })?;
+    Ok(Exit::Success)
+}
+
+fn validate(args: &PuzzleArgs) -> Result<Exit, Exit> {
+    let puzzle = load(args)?;
+    let validation = Validation::from(batch::solve(
+        &solver(&puzzle, &args.strategies)?,
+        puzzle.grid,
+    ));
+    match args.format {
+        Format::Text => println!(
+            "{}",
+            match validation.status {
+                Status::Solved => "valid: the puzzle has a unique solution",
+                Status::Multiple => "invalid: the puzzle has more than one solution",
+                _ => "invalid: the puzzle has no solution",
+            }
+        ),
+        Format::Json => print_json(&validation)?,
+    }
+    Ok(validation.status.into())
+}
+
+fn canonicalize(args: &PuzzleArgs) -> Result<Exit, Exit> {
+    let puzzle = load(args)?;
+    if !puzzle.constraints.is_empty() || puzzle.grid.regions.is_some() {
+        error!("Canonical forms are only defined for classic sudoku");
+        return Err(Exit::Invalid);
+    }
+    let canonical = canonical::canonicalize(&puzzle.grid);
+    match args.format {
+        Format::Text => println!("{}", canonical),
+        Format::Json => print_json(&Canonical { canonical })?,
+    }
+    Ok(Exit::Success)
+}
+
+fn bench(args: &BenchArgs) -> Result<Exit, Exit> {
+    let input = read_input(args.input.as_deref(), true)?;
+    let puzzle = Puzzle {
+        grid: Grid::default(),
+        constraints: args.constraints.constraints(),
+    };
+    let extra_houses = puzzle.extra_houses();
+    let solver = solver(&puzzle, &args.strategies)?;
+    let result = match &args.output {
+        Some(path) => File::create(path)
+            .and_then(|mut file| batch::run(&solver, &input, &extra_houses, &mut file)),
+        None => batch::run(
+            &solver,
+            &input,
+            &extra_houses,
+            &mut std::io::stdout().lock(),
+        ),
+    };
+    match result {
+        Ok(stats) => {
+            eprint!("{}", stats);
+            Ok(Exit::Success)
+        }
+        Err(e) => {
+            error!("Failed to write results: {}", e);
+            Err(Exit::Failure)
+        }
+    }
+}
+
+#[cfg(feature = "serve")]
+fn serve(args: &ServeArgs) -> Result<Exit, Exit> {
+    let config = args
+        .strategies
+        .config()
+        .and_then(|config| {
+            // Checked once up front, rather than failing every request
+            let puzzle = Puzzle {
+                grid: Grid::default(),
+                constraints: vec![],
+            };
+            config.apply(&mut puzzle.solver())?;
+            Ok(config)
+        })
+        .map_err(|e| {
+            error!("Invalid strategy config: {}", e);
+            Exit::Invalid
+        })?;
+    let workers = args.workers.map_or_else(
+        || std::thread::available_parallelism().map_or(4, |n| n.get()),
+        |n| n.get(),
+    );
+    let api = server::Api::new(config, args.timeout);
+    server::serve(api, args.port, workers, args.allow_origin.clone()).map_err(|e| {
+        error!("Failed to start the server: {}", e);
+        Exit::Failure
+    })?;
+    Ok(Exit::Success)
+}
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+
+    use crate::Cli;
+
+    #[test]
+    fn test_args() {
+        Cli::command().debug_assert();
     }
-    Ok(())
 }
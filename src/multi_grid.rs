@@ -0,0 +1,298 @@
+use std::str::FromStr;
+
+use itertools::Itertools;
+use log::{info, trace};
+
+use crate::{
+    grid::{Cell, Grid},
+    parser::parse_grid,
+    solver::Solver,
+};
+
+/// An arrangement of overlapping 9x9 grids on a larger canvas
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Four corner grids, each sharing one box with a fifth grid in the middle
+    Samurai,
+    /// Four grids on a 12x12 canvas, each offset by one box from its neighbours
+    Butterfly,
+    /// Two grids where the bottom right box of one is the top left box of the other
+    Twodoku,
+}
+impl Layout {
+    /// Canvas position of the top left cell of each grid
+    pub fn offsets(&self) -> Vec<(usize, usize)> {
+        match self {
+            Layout::Samurai => vec![(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)],
+            Layout::Butterfly => vec![(0, 0), (0, 3), (3, 0), (3, 3)],
+            Layout::Twodoku => vec![(0, 0), (6, 6)],
+        }
+    }
+}
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "samurai" => Ok(Layout::Samurai),
+            "butterfly" => Ok(Layout::Butterfly),
+            "twodoku" => Ok(Layout::Twodoku),
+            _ => Err(format!(
+                "unknown layout `{}`, expected samurai, butterfly or twodoku",
+                s
+            )),
+        }
+    }
+}
+
+/// A cell of one of the grids, as (grid, row, col)
+type Position = (usize, usize, usize);
+
+/// Several grids whose overlapping cells must hold the same digit
+#[derive(Clone)]
+pub struct MultiGrid {
+    pub grids: Vec<Grid>,
+    offsets: Vec<(usize, usize)>,
+    /// Pairs of cells from different grids which lie on the same canvas square
+    shared: Vec<[Position; 2]>,
+}
+impl MultiGrid {
+    pub fn new(layout: Layout) -> MultiGrid {
+        let offsets = layout.offsets();
+        let mut shared = Vec::new();
+        for ((a, (ar, ac)), (b, (br, bc))) in offsets.iter().enumerate().tuple_combinations() {
+            for (r, c) in (0..9).cartesian_product(0..9) {
+                let (row, col) = (ar + r, ac + c);
+                if (*br..br + 9).contains(&row) && (*bc..bc + 9).contains(&col) {
+                    shared.push([(a, r, c), (b, row - br, col - bc)]);
+                }
+            }
+        }
+        MultiGrid {
+            grids: vec![Grid::default(); offsets.len()],
+            offsets,
+            shared,
+        }
+    }
+
+    /// Reads one grid per non-empty line, in the order given by `Layout::offsets`.
+    /// Shared cells may be given in any of their grids, but must not disagree
+    pub fn parse(layout: Layout, input: &str) -> Result<MultiGrid, String> {
+        let mut multi = MultiGrid::new(layout);
+        let lines = input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+        if lines.len() != multi.grids.len() {
+            return Err(format!(
+                "{:?} layout needs {} grids, one per line, found {}",
+                layout,
+                multi.grids.len(),
+                lines.len()
+            ));
+        }
+        for (i, (grid, line)) in multi.grids.iter_mut().zip(lines).enumerate() {
+            *grid = parse_grid(line).map_err(|e| format!("grid {}: {}", i + 1, e))?;
+        }
+        for [(a, ar, ac), (b, br, bc)] in &multi.shared {
+            if let (Cell::Solved(x), Cell::Solved(y)) =
+                (multi.grids[*a][*ar][*ac], multi.grids[*b][*br][*bc])
+            {
+                if x != y {
+                    return Err(format!(
+                        "grid {} r{}c{} is {} but grid {} r{}c{} is {}",
+                        a + 1,
+                        ar + 1,
+                        ac + 1,
+                        x + 1,
+                        b + 1,
+                        br + 1,
+                        bc + 1,
+                        y + 1
+                    ));
+                }
+            }
+        }
+        Ok(multi)
+    }
+
+    pub fn solved(&self) -> bool {
+        self.grids.iter().all(|grid| grid.solved())
+    }
+    /// Whether any grid is broken, or two copies of a shared cell disagree
+    pub fn broken(&self) -> bool {
+        self.grids.iter().any(|grid| grid.broken())
+            || self.shared.iter().any(|[(a, ar, ac), (b, br, bc)]| {
+                matches!((self.grids[*a][*ar][*ac], self.grids[*b][*br][*bc]),
+                    (Cell::Solved(x), Cell::Solved(y)) if x != y)
+            })
+    }
+
+    /// Limits each copy of a shared cell to the digits allowed in every other copy
+    pub fn sync(&mut self) -> bool {
+        trace!("Syncing shared cells");
+        let mut result = false;
+        for [(a, ar, ac), (b, br, bc)] in self.shared.clone() {
+            let allowed_a = allowed(&self.grids[a][ar][ac]);
+            let allowed_b = allowed(&self.grids[b][br][bc]);
+            result |= self.grids[a][ar][ac].restrict(&allowed_b);
+            result |= self.grids[b][br][bc].restrict(&allowed_a);
+        }
+        result
+    }
+
+    /// Syncs shared cells, then takes a step in every grid
+    pub fn step(&mut self, solver: &Solver) -> bool {
+        let mut result = self.sync();
+        for grid in &mut self.grids {
+            result |= solver.step(grid);
+        }
+        result
+    }
+
+    /// Guesses in the cell with the fewest candidates across all grids, leaving the solution
+    /// in `self` if one is found
+    pub fn backtrack(&mut self, solver: &Solver) -> bool {
+        let target = self
+            .grids
+            .iter()
+            .enumerate()
+            .flat_map(|(g, grid)| {
+                (0..9)
+                    .cartesian_product(0..9)
+                    .map(move |(r, c)| (g, r, c, grid[r][c]))
+            })
+            .filter_map(|(g, r, c, cell)| match cell {
+                Cell::Unsolved(_) => Some(((g, r, c), cell.candidates())),
+                _ => None,
+            })
+            .min_by_key(|(_, cands)| cands.len());
+        let Some(((g, r, c), cands)) = target else {
+            return false;
+        };
+
+        for cand in cands {
+            let mut copy = self.clone();
+            copy.grids[g][r][c] = Cell::Solved(cand);
+            trace!("Trying a {} in grid {} R{}C{}...", cand + 1, g + 1, r, c);
+            while copy.step(solver) {
+                trace!("{}", copy);
+            }
+            if copy.broken() {
+                trace!("Backtracking failed, backing up");
+            } else if copy.solved() {
+                info!("Solution found!\n{}", copy);
+                *self = copy;
+                return true;
+            } else {
+                trace!("Backtracking further...");
+                if copy.backtrack(solver) {
+                    *self = copy;
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+impl std::fmt::Display for MultiGrid {
+    /// Draws the grids on their shared canvas, with a gap between boxes
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.offsets.iter().map(|(r, _)| r + 9).max().unwrap_or(0);
+        let width = self.offsets.iter().map(|(_, c)| c + 9).max().unwrap_or(0);
+        for row in 0..height {
+            if row % 3 == 0 && row > 0 {
+                f.write_str("\n")?;
+            }
+            let mut line = String::new();
+            for col in 0..width {
+                if col % 3 == 0 && col > 0 {
+                    line.push(' ');
+                }
+                let cell = self
+                    .offsets
+                    .iter()
+                    .zip(&self.grids)
+                    .find_map(|((r, c), grid)| {
+                        ((*r..r + 9).contains(&row) && (*c..c + 9).contains(&col))
+                            .then(|| grid[row - r][col - c])
+                    });
+                match cell {
+                    Some(cell) => line.push_str(&format!(" {} ", cell)),
+                    None => line.push_str("   "),
+                }
+            }
+            f.write_str(line.trim_end())?;
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+fn allowed(cell: &Cell) -> [bool; 9] {
+    let mut allowed = [false; 9];
+    for n in cell.options() {
+        allowed[n] = true;
+    }
+    allowed
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        basic_sudoku::{basic_elimination, hidden_singles, naked_singles},
+        grid::{Cell, Grid},
+        multi_grid::{Layout, MultiGrid},
+        solver::Solver,
+    };
+
+    #[test]
+    fn test_layouts() {
+        // Each corner grid of a samurai shares one box with the middle grid
+        assert_eq!(MultiGrid::new(Layout::Samurai).shared.len(), 36);
+        assert_eq!(MultiGrid::new(Layout::Twodoku).shared.len(), 9);
+        assert_eq!(MultiGrid::new(Layout::Butterfly).shared.len(), 288);
+        assert!("jigsaw".parse::<Layout>().is_err());
+    }
+
+    #[test]
+    fn test_sync() {
+        let mut multi = MultiGrid::new(Layout::Twodoku);
+        multi.grids[0][6][6] = Cell::Solved(3);
+        multi.grids[1][0][1] =
+            Cell::Unsolved([true, true, false, false, false, false, false, false, false]);
+
+        assert!(multi.sync());
+
+        assert_eq!(multi.grids[1][0][0].candidates(), vec![3]);
+        assert_eq!(multi.grids[0][6][7].candidates(), vec![0, 1]);
+        assert_eq!(multi.grids[0][0][0], Cell::Unsolved([true; 9]));
+    }
+
+    #[test]
+    fn test_conflicting_givens() {
+        let first = format!("{}1{}", ".".repeat(60), ".".repeat(20));
+        let second = format!("2{}", ".".repeat(80));
+        let input = format!("{}\n{}\n", first, second);
+
+        assert!(MultiGrid::parse(Layout::Twodoku, &input).is_err());
+    }
+
+    #[test]
+    fn test_backtrack() {
+        let mut solver = Solver::new();
+        solver.add_strategy("naked-singles", naked_singles);
+        solver.add_strategy("basic-elimination", basic_elimination);
+        solver.add_strategy("hidden-singles", hidden_singles);
+        let mut multi = MultiGrid::new(Layout::Twodoku);
+
+        assert!(multi.backtrack(&solver));
+
+        assert!(multi.solved() && !multi.broken());
+        for (r, c) in [(0, 0), (1, 2), (2, 1)] {
+            assert_eq!(multi.grids[0][r + 6][c + 6], multi.grids[1][r][c]);
+        }
+        assert!(multi.grids.iter().all(|grid: &Grid| !grid.broken()));
+    }
+}
@@ -0,0 +1,341 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    str::FromStr,
+};
+
+use log::trace;
+
+use crate::{
+    grid::{Cell, Grid},
+    sums::sum_options,
+};
+
+/// A clue outside the grid, and the cells it looks along, nearest first
+#[derive(Clone, Debug)]
+pub struct OutsideClue {
+    pub cells: Vec<(usize, usize)>,
+    pub value: usize,
+}
+
+/// Parses a position just outside the grid, e.g. `r0c3` above column 3 or `r5c10` right of row 5
+fn parse_outside(s: &str) -> Result<(isize, isize), String> {
+    let lower = s.trim().to_ascii_lowercase();
+    let coords = lower
+        .strip_prefix('r')
+        .and_then(|rest| rest.split_once('c'))
+        .and_then(|(r, c)| Some((r.parse::<isize>().ok()?, c.parse::<isize>().ok()?)));
+    match coords {
+        Some((r, c))
+            if (0..=10).contains(&r)
+                && (0..=10).contains(&c)
+                && [r, c].iter().any(|n| *n == 0 || *n == 10) =>
+        {
+            Ok((r - 1, c - 1))
+        }
+        _ => Err(format!(
+            "invalid outside position `{}`, expected e.g. `r0c3` or `r5c10`",
+            s.trim()
+        )),
+    }
+}
+
+/// Collects the cells from `start` (outside the grid) stepping in `direction` until leaving it
+fn walk(start: (isize, isize), direction: (isize, isize)) -> Vec<(usize, usize)> {
+    let mut cells = Vec::new();
+    let (mut r, mut c) = (start.0 + direction.0, start.1 + direction.1);
+    while (0..9).contains(&r) && (0..9).contains(&c) {
+        cells.push((r as usize, c as usize));
+        (r, c) = (r + direction.0, c + direction.1);
+    }
+    cells
+}
+
+fn split_value(s: &str) -> Result<(&str, usize), String> {
+    let Some((position, value)) = s.split_once('=') else {
+        return Err(format!("invalid clue `{}`, expected POSITION=VALUE", s));
+    };
+    let value = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid clue value `{}`", value.trim()))?;
+    Ok((position, value))
+}
+
+/// A clue on a row or column, looking into the grid from the side it sits on
+#[derive(Clone, Debug)]
+pub struct EdgeClue(pub OutsideClue);
+impl FromStr for EdgeClue {
+    type Err = String;
+
+    /// Parses `POSITION=VALUE`, e.g. `r0c3=15` for a clue above column 3
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (position, value) = split_value(s)?;
+        let (r, c) = parse_outside(position)?;
+        let direction = match (r, c) {
+            (-1, 0..=8) => (1, 0),
+            (9, 0..=8) => (-1, 0),
+            (0..=8, -1) => (0, 1),
+            (0..=8, 9) => (0, -1),
+            _ => return Err(format!("`{}` is not beside a row or column", position)),
+        };
+        Ok(EdgeClue(OutsideClue {
+            cells: walk((r, c), direction),
+            value,
+        }))
+    }
+}
+
+/// A little killer clue: the sum of the diagonal it points along, where digits may repeat
+#[derive(Clone, Debug)]
+pub struct DiagonalClue(pub OutsideClue);
+impl FromStr for DiagonalClue {
+    type Err = String;
+
+    /// Parses `POSITION:DIRECTION=VALUE`, e.g. `r0c3:se=15`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (position, value) = split_value(s)?;
+        let Some((position, direction)) = position.split_once(':') else {
+            return Err(format!(
+                "invalid clue `{}`, expected POSITION:DIRECTION=VALUE",
+                s
+            ));
+        };
+        let direction = match direction.trim().to_ascii_lowercase().as_str() {
+            "ne" => (-1, 1),
+            "nw" => (-1, -1),
+            "se" => (1, 1),
+            "sw" => (1, -1),
+            _ => {
+                return Err(format!(
+                    "invalid direction `{}`, expected ne, nw, se or sw",
+                    direction
+                ))
+            }
+        };
+        let cells = walk(parse_outside(position)?, direction);
+        if cells.is_empty() {
+            return Err(format!("clue `{}` does not point into the grid", s));
+        }
+        Ok(DiagonalClue(OutsideClue { cells, value }))
+    }
+}
+
+pub fn little_killers(grid: &mut Grid, clues: &[DiagonalClue]) -> bool {
+    trace!("Searching for little killer sums");
+    let mut result = false;
+    for DiagonalClue(clue) in clues {
+        let Some(options) = sum_options(grid, &clue.cells, &BTreeSet::from([clue.value])) else {
+            continue;
+        };
+        for ((r, c), allowed) in clue.cells.iter().zip(options.candidates.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+pub fn x_sums(grid: &mut Grid, clues: &[EdgeClue]) -> bool {
+    trace!("Searching for X-sums");
+    let mut result = false;
+    'clues: for EdgeClue(clue) in clues {
+        let (r, c) = clue.cells[0];
+        let mut allowed = vec![[false; 9]; clue.cells.len()];
+        for x in grid[r][c].options() {
+            // With the first digit fixed at X, the first X cells must make the clue
+            let mut trial = grid.clone();
+            trial[r][c] = Cell::Solved(x);
+            let counted = &clue.cells[..=x];
+            let Some(options) = sum_options(&trial, counted, &BTreeSet::from([clue.value])) else {
+                continue 'clues;
+            };
+            if options.sums.is_empty() {
+                continue;
+            }
+            for (allowed, cands) in allowed.iter_mut().zip(options.candidates.iter()) {
+                for (allowed, cand) in allowed.iter_mut().zip(cands) {
+                    *allowed |= cand;
+                }
+            }
+            for (allowed, (rr, cc)) in allowed.iter_mut().zip(clue.cells.iter()).skip(x + 1) {
+                for n in grid[*rr][*cc].options() {
+                    allowed[n] = true;
+                }
+            }
+        }
+        for ((r, c), allowed) in clue.cells.iter().zip(allowed.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+/// Progress along a skyscraper line: the position reached, the tallest digit so far, how many
+/// digits are visible and which digits have been used
+type Sightline = (usize, Option<usize>, usize, u16);
+
+struct Skyline {
+    options: Vec<Vec<usize>>,
+    clue: usize,
+    feasible: HashMap<Sightline, bool>,
+    visited: HashSet<Sightline>,
+    supported: Vec<[bool; 9]>,
+    /// The earliest position after a feasible 9, from which nothing else is visible
+    hidden_from: usize,
+}
+impl Skyline {
+    fn place((depth, tallest, visible, used): Sightline, n: usize) -> Sightline {
+        let seen = tallest.is_none_or(|t| n > t);
+        let tallest = if seen { Some(n) } else { tallest };
+        (depth + 1, tallest, visible + seen as usize, used | 1 << n)
+    }
+
+    /// Whether the line can be finished from this point to see exactly the clue
+    fn feasible(&mut self, state: Sightline) -> bool {
+        let (depth, tallest, visible, used) = state;
+        if tallest == Some(8) {
+            return visible == self.clue;
+        }
+        // Every digit taller than the tallest so far could add at most one more building
+        let taller = (tallest.map_or(0, |t| t + 1)..9)
+            .filter(|n| used & 1 << n == 0)
+            .count()
+            .min(self.options.len() - depth);
+        if depth == self.options.len() || visible + taller < self.clue {
+            return false;
+        }
+        if let Some(result) = self.feasible.get(&state) {
+            return *result;
+        }
+        let result = (0..self.options[depth].len()).any(|i| {
+            let n = self.options[depth][i];
+            used & 1 << n == 0 && self.feasible(Skyline::place(state, n))
+        });
+        self.feasible.insert(state, result);
+        result
+    }
+
+    /// Marks every digit placed by some feasible fill passing through this point
+    fn mark(&mut self, state: Sightline) {
+        let (depth, tallest, _, used) = state;
+        if tallest == Some(8) {
+            self.hidden_from = self.hidden_from.min(depth);
+            return;
+        }
+        if !self.visited.insert(state) {
+            return;
+        }
+        for i in 0..self.options[depth].len() {
+            let n = self.options[depth][i];
+            let next = Skyline::place(state, n);
+            if used & 1 << n == 0 && self.feasible(next) {
+                self.supported[depth][n] = true;
+                self.mark(next);
+            }
+        }
+    }
+}
+
+pub fn skyscrapers(grid: &mut Grid, clues: &[EdgeClue]) -> bool {
+    trace!("Searching for skyscrapers");
+    let mut result = false;
+    for EdgeClue(clue) in clues {
+        // A digit already fixed somewhere along the line cannot appear anywhere else on it
+        let mut options = clue
+            .cells
+            .iter()
+            .map(|(r, c)| grid[*r][*c].options())
+            .collect::<Vec<_>>();
+        let fixed = options
+            .iter()
+            .enumerate()
+            .filter(|(_, opts)| opts.len() == 1)
+            .map(|(i, opts)| (i, opts[0]))
+            .collect::<Vec<_>>();
+        for (i, opts) in options.iter_mut().enumerate() {
+            opts.retain(|n| fixed.iter().all(|(j, m)| i == *j || n != m));
+        }
+        let mut skyline = Skyline {
+            options,
+            clue: clue.value,
+            feasible: HashMap::new(),
+            visited: HashSet::new(),
+            supported: vec![[false; 9]; clue.cells.len()],
+            hidden_from: clue.cells.len(),
+        };
+        if skyline.feasible((0, None, 0, 0)) {
+            skyline.mark((0, None, 0, 0));
+        }
+        for i in skyline.hidden_from..clue.cells.len() {
+            for n in skyline.options[i].clone() {
+                skyline.supported[i][n] = true;
+            }
+        }
+        for ((r, c), allowed) in clue.cells.iter().zip(skyline.supported.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::{Cell, Grid},
+        outside_strategies::{little_killers, skyscrapers, x_sums, DiagonalClue, EdgeClue},
+    };
+
+    #[test]
+    fn test_little_killers() {
+        let mut grid = Grid::default();
+        let clue: DiagonalClue = "r0c6:sw=7".parse().unwrap();
+        assert_eq!(clue.0.cells, vec![(0, 4), (1, 3), (2, 2), (3, 1), (4, 0)]);
+
+        let short: DiagonalClue = "r7c0:se=3".parse().unwrap();
+
+        assert!(little_killers(&mut grid, &[clue, short]));
+
+        // Two pairs sharing a box need a 1 and a 2 each, so the middle cell is left with 1
+        assert_eq!(grid[0][4].candidates(), vec![0, 1]);
+        assert_eq!(grid[4][0].candidates(), vec![0, 1]);
+        assert_eq!(grid[2][2].candidates(), vec![0]);
+        assert_eq!(grid[7][0].candidates(), vec![0, 1]);
+        assert_eq!(grid[8][1].candidates(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_x_sums() {
+        let mut grid = Grid::default();
+        grid[0][1] = Cell::Solved(2);
+        let clue: EdgeClue = "r1c0=10".parse().unwrap();
+
+        assert!(x_sums(&mut grid, &[clue]));
+
+        // Only X = 4 works, with a 1 and a 2 filling the rest of the sum
+        assert_eq!(grid[0][0].candidates(), vec![3]);
+        assert_eq!(grid[0][2].candidates(), vec![0, 1]);
+        assert_eq!(grid[0][3].candidates(), vec![0, 1]);
+        assert_eq!(grid[0][4], Cell::Unsolved([true; 9]));
+    }
+
+    #[test]
+    fn test_skyscrapers() {
+        let mut grid = Grid::default();
+        grid[4][2] = Cell::Solved(8);
+        let clues: Vec<EdgeClue> = ["r0c1=9", "r1c10=1", "r5c0=2", "r10c9=8"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+
+        assert!(skyscrapers(&mut grid, &clues));
+
+        // Seeing all nine means the column climbs 1 to 9
+        assert!((0..9).all(|r| grid[r][0].candidates() == vec![r]));
+        // Seeing one means the 9 is nearest
+        assert_eq!(grid[0][8].candidates(), vec![8]);
+        // Row 5 starts with the 5 from the column, so nothing before the 9 may top it
+        assert_eq!(grid[4][1].candidates(), vec![0, 1, 2, 3]);
+        assert_eq!(grid[4][3].candidates(), vec![0, 1, 2, 3, 5, 6, 7]);
+        // Seeing eight from below leaves room for just one swap of neighbours
+        assert_eq!(grid[8][8].candidates(), vec![0, 1]);
+    }
+}
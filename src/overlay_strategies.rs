@@ -0,0 +1,201 @@
+use std::str::FromStr;
+
+use itertools::Itertools;
+use log::trace;
+
+use crate::{
+    adjacency_strategies::parse_pair,
+    grid::{parse_cell, parse_cells, Grid},
+};
+
+/// A marking drawn over the grid which limits the digits of the cells it covers
+#[derive(Clone, Debug)]
+pub enum Overlay {
+    /// Grey squares: the cells hold even digits
+    Even(Vec<(usize, usize)>),
+    /// Grey circles: the cells hold odd digits
+    Odd(Vec<(usize, usize)>),
+    /// An inequality sign between adjacent cells: the first holds the greater digit
+    Greater([(usize, usize); 2]),
+    /// A circle on a grid point: the digits (repeats allowed) appear in the four cells around it
+    Quadruple {
+        cells: [(usize, usize); 4],
+        digits: Vec<usize>,
+    },
+}
+impl FromStr for Overlay {
+    type Err = String;
+
+    /// Parses `KIND:SPEC`: `even:CELLS`, `odd:CELLS`, `gt:A,B` or `lt:A,B` for adjacent cells
+    /// A and B, or `quad:CELL=DIGITS` for the point at the bottom right of CELL
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((kind, spec)) = s.split_once(':') else {
+            return Err(format!("invalid overlay `{}`, expected KIND:SPEC", s));
+        };
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "even" => Ok(Overlay::Even(parse_cells(spec)?)),
+            "odd" => Ok(Overlay::Odd(parse_cells(spec)?)),
+            "gt" => Ok(Overlay::Greater(parse_pair(spec)?)),
+            "lt" => {
+                let [a, b] = parse_pair(spec)?;
+                Ok(Overlay::Greater([b, a]))
+            }
+            "quad" => {
+                let Some((cell, digits)) = spec.split_once('=') else {
+                    return Err(format!(
+                        "invalid quadruple `{}`, expected CELL=DIGITS",
+                        spec
+                    ));
+                };
+                let (r, c) = parse_cell(cell)?;
+                if r == 8 || c == 8 {
+                    return Err(format!(
+                        "quadruple at `{}` has no point below and right",
+                        cell
+                    ));
+                }
+                let digits = digits
+                    .trim()
+                    .chars()
+                    .map(|d| match d.to_digit(10) {
+                        Some(d @ 1..=9) => Ok(d as usize - 1),
+                        _ => Err(format!("invalid quadruple digit `{}`", d)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if digits.is_empty() || digits.len() > 4 {
+                    return Err(format!("quadruple `{}` needs one to four digits", spec));
+                }
+                Ok(Overlay::Quadruple {
+                    cells: [(r, c), (r, c + 1), (r + 1, c), (r + 1, c + 1)],
+                    digits,
+                })
+            }
+            _ => Err(format!(
+                "unknown overlay `{}`, expected even, odd, gt, lt or quad",
+                kind
+            )),
+        }
+    }
+}
+
+pub fn parity(grid: &mut Grid, overlays: &[Overlay]) -> bool {
+    trace!("Searching for parity markers");
+    let mut result = false;
+    for overlay in overlays {
+        // Digits are 0-indexed, so even digits sit at odd indices
+        let (cells, allowed) = match overlay {
+            Overlay::Even(cells) => (cells, std::array::from_fn(|n| n % 2 == 1)),
+            Overlay::Odd(cells) => (cells, std::array::from_fn(|n| n % 2 == 0)),
+            _ => continue,
+        };
+        for (r, c) in cells {
+            result |= grid[*r][*c].restrict(&allowed);
+        }
+    }
+    result
+}
+
+pub fn inequalities(grid: &mut Grid, overlays: &[Overlay]) -> bool {
+    trace!("Searching for inequalities");
+    let mut result = false;
+    for overlay in overlays {
+        let Overlay::Greater([(gr, gc), (lr, lc)]) = *overlay else {
+            continue;
+        };
+        let lowest = grid[lr][lc].options().into_iter().min().unwrap_or(9);
+        let highest = grid[gr][gc].options().into_iter().max().unwrap_or(0);
+        result |= grid[gr][gc].restrict(&std::array::from_fn(|n| n > lowest));
+        result |= grid[lr][lc].restrict(&std::array::from_fn(|n| n < highest));
+    }
+    result
+}
+
+pub fn quadruples(grid: &mut Grid, overlays: &[Overlay]) -> bool {
+    trace!("Searching for quadruples");
+    let mut result = false;
+    for overlay in overlays {
+        let Overlay::Quadruple { cells, digits } = overlay else {
+            continue;
+        };
+        let mut allowed = [[false; 9]; 4];
+        let fills = cells
+            .iter()
+            .map(|(r, c)| grid[*r][*c].options())
+            .multi_cartesian_product()
+            .filter(|fill| {
+                (0..4)
+                    .tuple_combinations()
+                    .all(|(i, j)| !grid.sees(cells[i], cells[j]) || fill[i] != fill[j])
+                    && digits.iter().all(|d| {
+                        let needed = digits.iter().filter(|n| *n == d).count();
+                        fill.iter().filter(|n| *n == d).count() >= needed
+                    })
+            });
+        for fill in fills {
+            for (allowed, n) in allowed.iter_mut().zip(fill) {
+                allowed[n] = true;
+            }
+        }
+        for ((r, c), allowed) in cells.iter().zip(allowed.iter()) {
+            result |= grid[*r][*c].restrict(allowed);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::{Cell, Grid},
+        overlay_strategies::{inequalities, parity, quadruples, Overlay},
+    };
+
+    #[test]
+    fn test_parity() {
+        let mut grid = Grid::default();
+        let overlays: Vec<Overlay> = ["even:r1c1,r2c2", "odd:r9c9"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+
+        assert!(parity(&mut grid, &overlays));
+
+        assert_eq!(grid[0][0].candidates(), vec![1, 3, 5, 7]);
+        assert_eq!(grid[1][1].candidates(), vec![1, 3, 5, 7]);
+        assert_eq!(grid[8][8].candidates(), vec![0, 2, 4, 6, 8]);
+    }
+
+    #[test]
+    fn test_inequalities() {
+        let mut grid = Grid::default();
+        grid[0][2] = Cell::Solved(6);
+        let overlays: Vec<Overlay> = ["gt:r1c1,r1c2", "lt:r1c2,r1c3"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+
+        assert!(inequalities(&mut grid, &overlays));
+
+        // r1c2 is below 7, so at most 6, and r1c1 is above r1c2, so at least 2
+        assert_eq!(grid[0][1].candidates(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(grid[0][0].candidates(), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_quadruples() {
+        let mut grid = Grid::default();
+        grid[0][0] = Cell::Unsolved([true, true, false, false, false, false, false, false, false]);
+        grid[0][1] = Cell::Unsolved([true, true, false, false, false, false, false, false, false]);
+        grid[1][0] = Cell::Unsolved([true, true, true, false, false, false, false, false, false]);
+        let overlays: Vec<Overlay> = ["quad:r1c1=123"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+
+        assert!(quadruples(&mut grid, &overlays));
+
+        // 1 and 2 fill the top pair, so the 3 must go in r2c1
+        assert_eq!(grid[1][0].candidates(), vec![2]);
+        assert_eq!(grid[1][1].candidates(), vec![3, 4, 5, 6, 7, 8]);
+    }
+}
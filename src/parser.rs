@@ -0,0 +1,264 @@
+use itertools::Itertools;
+
+use crate::grid::{Cell, Grid};
+
+/// Why a puzzle string couldn't be read as a grid
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The puzzle didn't have exactly 81 cells, ignoring whitespace
+    WrongLength(usize),
+    /// A character which is neither a digit nor a blank, at the cell (row, col) it would fill
+    InvalidCharacter {
+        character: char,
+        cell: (usize, usize),
+    },
+    /// Two givens of the same (0-indexed) digit share a house
+    ConflictingGivens {
+        digit: usize,
+        cells: [(usize, usize); 2],
+    },
+}
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::WrongLength(n) => write!(f, "expected 81 cells, found {}", n),
+            ParseError::InvalidCharacter {
+                character,
+                cell: (r, c),
+            } => write!(
+                f,
+                "invalid character `{}` at r{}c{}, expected a digit 1-9, or one of `.0-_*` for an empty cell",
+                character,
+                r + 1,
+                c + 1
+            ),
+            ParseError::ConflictingGivens {
+                digit,
+                cells: [(r1, c1), (r2, c2)],
+            } => write!(
+                f,
+                "conflicting givens: {} at both r{}c{} and r{}c{}",
+                digit + 1,
+                r1 + 1,
+                c1 + 1,
+                r2 + 1,
+                c2 + 1
+            ),
+        }
+    }
+}
+impl std::error::Error for ParseError {}
+
+/// Reads 81 cells row by row, ignoring whitespace, where `.`, `0`, `-`, `_` and `*` are empty
+/// cells
+pub fn parse_givens(input: &str) -> Result<Grid, ParseError> {
+    let chars = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    let mut grid = Grid::default();
+    for (i, character) in chars.iter().take(81).enumerate() {
+        grid[i / 9][i % 9] = match character {
+            '.' | '0' | '-' | '_' | '*' => Cell::Unsolved([true; 9]),
+            '1'..='9' => Cell::Solved(*character as usize - '1' as usize),
+            _ => {
+                return Err(ParseError::InvalidCharacter {
+                    character: *character,
+                    cell: (i / 9, i % 9),
+                })
+            }
+        };
+    }
+    if chars.len() != 81 {
+        return Err(ParseError::WrongLength(chars.len()));
+    }
+    Ok(grid)
+}
+
+/// The cells of a pencil-mark grid, skipping separator lines such as `+-----+` or `:-----:`
+fn pencil_mark_cells(input: &str) -> Vec<&str> {
+    input
+        .lines()
+        .filter(|line| !line.contains("--") || line.contains(|c: char| c.is_ascii_digit()))
+        .flat_map(|line| line.split(|c: char| c == '|' || c.is_whitespace()))
+        .filter(|cell| !cell.is_empty())
+        .collect()
+}
+
+/// Reads a 9×9 block of candidate strings, as written by [`Grid::to_pencil_marks`]. A single
+/// digit is a solved cell, `.` or `0` is a cell with every candidate and `-` one with none.
+/// A cell left with a single candidate can't be told apart from a solved one, so it comes back
+/// solved
+pub fn parse_pencil_marks(input: &str) -> Result<Grid, ParseError> {
+    let cells = pencil_mark_cells(input);
+    let mut grid = Grid::default();
+    for (i, marks) in cells.iter().take(81).enumerate() {
+        let mut cands = [false; 9];
+        for character in marks.chars() {
+            match character {
+                '1'..='9' => cands[character as usize - '1' as usize] = true,
+                '.' | '0' if marks.len() == 1 => cands = [true; 9],
+                '-' if marks.len() == 1 => {}
+                _ => {
+                    return Err(ParseError::InvalidCharacter {
+                        character,
+                        cell: (i / 9, i % 9),
+                    })
+                }
+            }
+        }
+        grid[i / 9][i % 9] = match marks.parse::<usize>() {
+            Ok(n @ 1..=9) => Cell::Solved(n - 1),
+            _ => Cell::Unsolved(cands),
+        };
+    }
+    if cells.len() != 81 {
+        return Err(ParseError::WrongLength(cells.len()));
+    }
+    Ok(grid)
+}
+
+/// Reads either format: pencil marks if the input has box separators or a cell per
+/// whitespace-separated word, otherwise 81 plain givens
+pub fn parse_puzzle(input: &str) -> Result<Grid, ParseError> {
+    if input.contains('|') || pencil_mark_cells(input).len() == 81 {
+        parse_pencil_marks(input)
+    } else {
+        parse_givens(input)
+    }
+}
+
+/// Checks that no two givens of the same digit share a house, including the grid's jigsaw
+/// regions and extra houses
+pub fn check_givens(grid: &Grid) -> Result<(), ParseError> {
+    let conflict = (0..81).tuple_combinations().find_map(|(a, b)| {
+        let (a, b) = ((a / 9, a % 9), (b / 9, b % 9));
+        match (grid[a.0][a.1], grid[b.0][b.1]) {
+            (Cell::Solved(x), Cell::Solved(y)) if x == y && grid.sees(a, b) => Some((x, [a, b])),
+            _ => None,
+        }
+    });
+    match conflict {
+        Some((digit, cells)) => Err(ParseError::ConflictingGivens { digit, cells }),
+        None => Ok(()),
+    }
+}
+
+/// Reads a standard puzzle, checking its givens against the rows, columns and boxes
+pub fn parse_grid(input: &str) -> Result<Grid, ParseError> {
+    let grid = parse_givens(input)?;
+    check_givens(&grid)?;
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::Cell,
+        houses::diagonals,
+        parser::{
+            check_givens, parse_givens, parse_grid, parse_pencil_marks, parse_puzzle, ParseError,
+        },
+    };
+
+    #[test]
+    fn test_parse_grid() {
+        let grid = parse_grid(
+            "53..7.... 6..195... .98....6. 8...6...3 4..8.3..1 7...2...6 .6....28. 000419005 ....8..79",
+        )
+        .unwrap();
+
+        assert_eq!(grid[0][0], Cell::Solved(4));
+        assert_eq!(grid[7][0], Cell::Unsolved([true; 9]));
+        assert_eq!(grid[7][8], Cell::Solved(4));
+
+        // Other tools write blanks as `-`, `_` or `*`
+        let blanks =
+            "53--7____6**195***.98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+        assert_eq!(
+            parse_grid(blanks).unwrap().to_line(),
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let blank = ".".repeat(81);
+
+        assert_eq!(
+            parse_grid(&blank[1..]).err(),
+            Some(ParseError::WrongLength(80))
+        );
+        assert_eq!(
+            parse_grid(&format!("{}.", blank)).err(),
+            Some(ParseError::WrongLength(82))
+        );
+        assert_eq!(
+            parse_grid(&format!("{}x{}", &blank[..12], &blank[13..])).err(),
+            Some(ParseError::InvalidCharacter {
+                character: 'x',
+                cell: (1, 3)
+            })
+        );
+        assert_eq!(
+            parse_grid(&format!(
+                "{}4{}4{}",
+                &blank[..3],
+                &blank[4..23],
+                &blank[24..]
+            ))
+            .err(),
+            Some(ParseError::ConflictingGivens {
+                digit: 3,
+                cells: [(0, 3), (2, 5)]
+            })
+        );
+
+        // r1c1 and r9c9 only conflict once the diagonals are houses
+        let mut grid = parse_givens(&format!("7{}7", &blank[2..])).unwrap();
+        assert!(check_givens(&grid).is_ok());
+        grid.extra_houses.extend(diagonals());
+        assert!(check_givens(&grid).is_err());
+    }
+
+    #[test]
+    fn test_pencil_marks() {
+        let mut grid = parse_grid(&format!("5{}", ".".repeat(80))).unwrap();
+        grid[0][1] = Cell::Unsolved([false, true, true, false, false, false, false, false, true]);
+        grid[8][8] = Cell::Unsolved([false; 9]);
+
+        let marks = grid.to_pencil_marks();
+        let lines: Vec<_> = marks.lines().collect();
+        assert_eq!(lines.len(), 13);
+        assert!(lines.iter().all(|line| line.len() == lines[0].len()));
+        assert!(lines[1].starts_with("| 5          239        123456789 |"));
+
+        let parsed = parse_puzzle(&marks).unwrap();
+        assert_eq!(parsed.cells, grid.cells);
+        assert_eq!(
+            parse_puzzle(&grid.to_line()).unwrap().cells[0][1],
+            Cell::default()
+        );
+
+        // Other tools draw the borders differently
+        let hodoku = marks.replace('+', ".").replacen(".-", ":-", 2);
+        assert_eq!(parse_pencil_marks(&hodoku).unwrap().cells, grid.cells);
+
+        assert_eq!(
+            parse_pencil_marks(&marks.replacen("239", "2a9", 1)).err(),
+            Some(ParseError::InvalidCharacter {
+                character: 'a',
+                cell: (0, 1)
+            })
+        );
+        assert_eq!(
+            parse_pencil_marks(&marks.replacen(" 5 ", " ", 1)).err(),
+            Some(ParseError::WrongLength(80))
+        );
+
+        // A last candidate is written as a plain digit, so it comes back solved
+        grid[4][4] = Cell::Unsolved([false, false, false, true, false, false, false, false, false]);
+        let parsed = parse_puzzle(&grid.to_pencil_marks()).unwrap();
+        assert_eq!(parsed[4][4], Cell::Solved(3));
+    }
+}
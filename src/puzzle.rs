@@ -0,0 +1,605 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    adjacency_strategies::{kropki, nonconsecutive, parse_pair, xv, Dot, DotKind, Xv, XvKind},
+    arrow_strategies::{arrows, Arrow},
+    basic_sudoku::{
+        basic_elimination, hidden_singles, hidden_tuples, naked_singles, naked_tuples,
+        pointing_tuples,
+    },
+    cage_strategies::{killer_cages, Cage},
+    chess_strategies::{bishops, diagonal_rooks, kings, knights, parse_digits, queens},
+    grid::{parse_cell, Cell, Grid, House},
+    houses::{diagonals, disjoint_groups, windoku},
+    import::{import, is_link},
+    line_strategies::{
+        between_lines, dutch_whispers, german_whispers, palindromes, region_sums, renban,
+        thermometers, zippers, Line,
+    },
+    outside_strategies::{little_killers, skyscrapers, x_sums, DiagonalClue, EdgeClue},
+    overlay_strategies::{inequalities, parity, quadruples, Overlay},
+    parser::{check_givens, parse_givens, parse_puzzle},
+    solver::Solver,
+};
+
+/// A variant rule, with its clue where it has one.
+/// In puzzle files each is a table with a `type`, and a `spec` written as for the matching
+/// command line flag, e.g. `{ type = "arrow", spec = "r1c1=r1c2,r1c3" }`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Constraint {
+    Antiking,
+    Antiknight,
+    Antiqueen {
+        #[serde(default = "all_digits", deserialize_with = "digits")]
+        digits: [bool; 9],
+    },
+    Antibishop {
+        #[serde(default = "all_digits", deserialize_with = "digits")]
+        digits: [bool; 9],
+    },
+    /// Rooks on the two long diagonals, so the digits can't repeat along either
+    AntirookDiagonal {
+        #[serde(default = "all_digits", deserialize_with = "digits")]
+        digits: [bool; 9],
+    },
+    Nonconsecutive,
+    Diagonal,
+    Windoku,
+    DisjointGroups,
+    Cage {
+        #[serde(deserialize_with = "parsed")]
+        spec: Cage,
+    },
+    Thermo {
+        #[serde(deserialize_with = "parsed")]
+        spec: Line,
+    },
+    Arrow {
+        #[serde(deserialize_with = "parsed")]
+        spec: Arrow,
+    },
+    GermanWhispers {
+        #[serde(deserialize_with = "parsed")]
+        spec: Line,
+    },
+    DutchWhispers {
+        #[serde(deserialize_with = "parsed")]
+        spec: Line,
+    },
+    Renban {
+        #[serde(deserialize_with = "parsed")]
+        spec: Line,
+    },
+    Palindrome {
+        #[serde(deserialize_with = "parsed")]
+        spec: Line,
+    },
+    RegionSum {
+        #[serde(deserialize_with = "parsed")]
+        spec: Line,
+    },
+    Zipper {
+        #[serde(deserialize_with = "parsed")]
+        spec: Line,
+    },
+    Between {
+        #[serde(deserialize_with = "parsed")]
+        spec: Line,
+    },
+    LittleKiller {
+        #[serde(deserialize_with = "parsed")]
+        spec: DiagonalClue,
+    },
+    XSum {
+        #[serde(deserialize_with = "parsed")]
+        spec: EdgeClue,
+    },
+    Skyscraper {
+        #[serde(deserialize_with = "parsed")]
+        spec: EdgeClue,
+    },
+    Overlay {
+        #[serde(deserialize_with = "parsed")]
+        spec: Overlay,
+    },
+    WhiteDot {
+        #[serde(deserialize_with = "pair")]
+        spec: [(usize, usize); 2],
+    },
+    BlackDot {
+        #[serde(deserialize_with = "pair")]
+        spec: [(usize, usize); 2],
+    },
+    AllDots,
+    XPair {
+        #[serde(deserialize_with = "pair")]
+        spec: [(usize, usize); 2],
+    },
+    VPair {
+        #[serde(deserialize_with = "pair")]
+        spec: [(usize, usize); 2],
+    },
+    AllXv,
+}
+
+fn all_digits() -> [bool; 9] {
+    [true; 9]
+}
+fn digits<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[bool; 9], D::Error> {
+    parse_digits(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+fn pair<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[(usize, usize); 2], D::Error> {
+    parse_pair(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+fn parsed<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+/// The layout of a puzzle file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PuzzleFile {
+    #[serde(default = "default_size")]
+    size: usize,
+    /// 81 cells row by row, with `.`, `0`, `-`, `_` or `*` for an empty cell; whitespace is
+    /// ignored
+    givens: String,
+    /// 81 region numbers 1-9 row by row for a jigsaw, instead of the usual boxes
+    regions: Option<String>,
+    /// Candidates to start from in some cells, e.g. `r1c2 = "126"`
+    #[serde(default)]
+    pencil_marks: BTreeMap<String, String>,
+    #[serde(default)]
+    constraints: Vec<Constraint>,
+}
+fn default_size() -> usize {
+    9
+}
+
+/// Arranges 81 region indices, row by row, checking that every region has 9 cells
+pub fn region_map(regions: &[usize]) -> Result<[[usize; 9]; 9], String> {
+    if regions.len() != 81 {
+        return Err(format!("regions need 81 cells, found {}", regions.len()));
+    }
+    if let Some(region) = (0..9).find(|i| regions.iter().filter(|r| *r == i).count() != 9) {
+        return Err(format!("region {} does not have 9 cells", region + 1));
+    }
+    Ok(std::array::from_fn(|r| {
+        std::array::from_fn(|c| regions[r * 9 + c])
+    }))
+}
+
+/// The name of every strategy `Puzzle::solver` can add, in the order it adds them
+pub const STRATEGIES: &[&str] = &[
+    "naked-singles",
+    "basic-elimination",
+    "antiking",
+    "antiknight",
+    "antiqueen",
+    "antibishop",
+    "antirook-diagonal",
+    "nonconsecutive",
+    "killer-cages",
+    "thermometers",
+    "arrows",
+    "german-whispers",
+    "dutch-whispers",
+    "renban",
+    "palindromes",
+    "region-sums",
+    "zippers",
+    "between-lines",
+    "little-killers",
+    "x-sums",
+    "skyscrapers",
+    "parity",
+    "inequalities",
+    "quadruples",
+    "kropki",
+    "xv",
+    "hidden-singles",
+    "naked-tuples",
+    "hidden-tuples",
+    "pointing-tuples",
+];
+
+/// A starting grid and the variant rules it is solved under
+pub struct Puzzle {
+    pub grid: Grid,
+    pub constraints: Vec<Constraint>,
+}
+impl Puzzle {
+    pub fn from_toml(s: &str) -> Result<Puzzle, String> {
+        toml::from_str::<PuzzleFile>(s)
+            .map_err(|e| e.to_string())?
+            .try_into()
+    }
+    pub fn from_json(s: &str) -> Result<Puzzle, String> {
+        serde_json::from_str::<PuzzleFile>(s)
+            .map_err(|e| e.to_string())?
+            .try_into()
+    }
+    /// Reads a puzzle from a link, the contents of a .toml or .json puzzle file, or a plain or
+    /// pencil-mark grid, going by the name of the file it was read from
+    pub fn parse(name: Option<&str>, text: &str) -> Result<Puzzle, String> {
+        match name {
+            Some(link) if is_link(link) => import(text),
+            Some(path) if path.ends_with(".toml") => Puzzle::from_toml(text),
+            Some(path) if path.ends_with(".json") => Puzzle::from_json(text),
+            _ => parse_puzzle(text)
+                .map(|grid| Puzzle {
+                    grid,
+                    constraints: vec![],
+                })
+                .map_err(|e| format!("Invalid puzzle: {}", e)),
+        }
+    }
+    /// Adds more constraints, and the houses of all constraints to the grid, checking that the
+    /// givens still don't repeat in a house
+    pub fn with_constraints(mut self, constraints: Vec<Constraint>) -> Result<Puzzle, String> {
+        self.constraints.extend(constraints);
+        let extra_houses = self.extra_houses();
+        self.grid.extra_houses.extend(extra_houses);
+        check_givens(&self.grid).map_err(|e| format!("Invalid puzzle: {}", e))?;
+        Ok(self)
+    }
+
+    /// Houses added by rules such as the Sudoku-X diagonals, to be added to the grid
+    pub fn extra_houses(&self) -> Vec<House> {
+        self.constraints
+            .iter()
+            .flat_map(|constraint| match constraint {
+                Constraint::Diagonal => diagonals(),
+                Constraint::Windoku => windoku(),
+                Constraint::DisjointGroups => disjoint_groups(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    /// Builds a solver running the basic strategies along with one for each kind of constraint
+    pub fn solver(&self) -> Solver {
+        let mut antiking = false;
+        let mut antiknight = false;
+        let mut antiqueen = None::<[bool; 9]>;
+        let mut antibishop = None::<[bool; 9]>;
+        let mut antirook_diagonal = None::<[bool; 9]>;
+        let mut nonconsecutive_rule = false;
+        let mut cages = vec![];
+        let mut thermos = vec![];
+        let mut arrow_list = vec![];
+        let mut german = vec![];
+        let mut dutch = vec![];
+        let mut renbans = vec![];
+        let mut palindrome_lines = vec![];
+        let mut region_sum_lines = vec![];
+        let mut zipper_lines = vec![];
+        let mut between = vec![];
+        let mut little_killer_clues = vec![];
+        let mut x_sum_clues = vec![];
+        let mut skyscraper_clues = vec![];
+        let (mut parity_overlays, mut inequality_overlays, mut quadruple_overlays) =
+            (vec![], vec![], vec![]);
+        let (mut dots, mut all_dots) = (vec![], false);
+        let (mut markers, mut all_xv) = (vec![], false);
+
+        let merge = |digits: Option<[bool; 9]>, more: [bool; 9]| {
+            let current = digits.unwrap_or([false; 9]);
+            Some(std::array::from_fn(|n| current[n] || more[n]))
+        };
+        for constraint in self.constraints.iter().cloned() {
+            match constraint {
+                Constraint::Antiking => antiking = true,
+                Constraint::Antiknight => antiknight = true,
+                Constraint::Antiqueen { digits } => antiqueen = merge(antiqueen, digits),
+                Constraint::Antibishop { digits } => antibishop = merge(antibishop, digits),
+                Constraint::AntirookDiagonal { digits } => {
+                    antirook_diagonal = merge(antirook_diagonal, digits)
+                }
+                Constraint::Nonconsecutive => nonconsecutive_rule = true,
+                Constraint::Diagonal | Constraint::Windoku | Constraint::DisjointGroups => {}
+                Constraint::Cage { spec } => cages.push(spec),
+                Constraint::Thermo { spec } => thermos.push(spec),
+                Constraint::Arrow { spec } => arrow_list.push(spec),
+                Constraint::GermanWhispers { spec } => german.push(spec),
+                Constraint::DutchWhispers { spec } => dutch.push(spec),
+                Constraint::Renban { spec } => renbans.push(spec),
+                Constraint::Palindrome { spec } => palindrome_lines.push(spec),
+                Constraint::RegionSum { spec } => region_sum_lines.push(spec),
+                Constraint::Zipper { spec } => zipper_lines.push(spec),
+                Constraint::Between { spec } => between.push(spec),
+                Constraint::LittleKiller { spec } => little_killer_clues.push(spec),
+                Constraint::XSum { spec } => x_sum_clues.push(spec),
+                Constraint::Skyscraper { spec } => skyscraper_clues.push(spec),
+                Constraint::Overlay { spec } => match spec {
+                    Overlay::Even(_) | Overlay::Odd(_) => parity_overlays.push(spec),
+                    Overlay::Greater(_) => inequality_overlays.push(spec),
+                    Overlay::Quadruple { .. } => quadruple_overlays.push(spec),
+                },
+                Constraint::WhiteDot { spec } => dots.push(Dot {
+                    kind: DotKind::White,
+                    cells: spec,
+                }),
+                Constraint::BlackDot { spec } => dots.push(Dot {
+                    kind: DotKind::Black,
+                    cells: spec,
+                }),
+                Constraint::AllDots => all_dots = true,
+                Constraint::XPair { spec } => markers.push(Xv {
+                    kind: XvKind::X,
+                    cells: spec,
+                }),
+                Constraint::VPair { spec } => markers.push(Xv {
+                    kind: XvKind::V,
+                    cells: spec,
+                }),
+                Constraint::AllXv => all_xv = true,
+            }
+        }
+
+        let mut solver = Solver::new();
+        solver.add_strategy("naked-singles", naked_singles);
+        solver.add_strategy("basic-elimination", basic_elimination);
+        if antiking {
+            solver.add_strategy("antiking", kings);
+        }
+        if antiknight {
+            solver.add_strategy("antiknight", knights)
+        }
+        if let Some(digits) = antiqueen {
+            solver.add_strategy("antiqueen", move |grid| queens(grid, &digits));
+        }
+        if let Some(digits) = antibishop {
+            solver.add_strategy("antibishop", move |grid| bishops(grid, &digits));
+        }
+        if let Some(digits) = antirook_diagonal {
+            solver.add_strategy("antirook-diagonal", move |grid| {
+                diagonal_rooks(grid, &digits)
+            });
+        }
+        if nonconsecutive_rule {
+            solver.add_strategy("nonconsecutive", nonconsecutive);
+        }
+        if !cages.is_empty() {
+            solver.add_strategy("killer-cages", move |grid| killer_cages(grid, &cages));
+        }
+        if !thermos.is_empty() {
+            solver.add_strategy("thermometers", move |grid| thermometers(grid, &thermos));
+        }
+        if !arrow_list.is_empty() {
+            solver.add_strategy("arrows", move |grid| arrows(grid, &arrow_list));
+        }
+        if !german.is_empty() {
+            solver.add_strategy("german-whispers", move |grid| {
+                german_whispers(grid, &german)
+            });
+        }
+        if !dutch.is_empty() {
+            solver.add_strategy("dutch-whispers", move |grid| dutch_whispers(grid, &dutch));
+        }
+        if !renbans.is_empty() {
+            solver.add_strategy("renban", move |grid| renban(grid, &renbans));
+        }
+        if !palindrome_lines.is_empty() {
+            solver.add_strategy("palindromes", move |grid| {
+                palindromes(grid, &palindrome_lines)
+            });
+        }
+        if !region_sum_lines.is_empty() {
+            solver.add_strategy("region-sums", move |grid| {
+                region_sums(grid, &region_sum_lines)
+            });
+        }
+        if !zipper_lines.is_empty() {
+            solver.add_strategy("zippers", move |grid| zippers(grid, &zipper_lines));
+        }
+        if !between.is_empty() {
+            solver.add_strategy("between-lines", move |grid| between_lines(grid, &between));
+        }
+        if !little_killer_clues.is_empty() {
+            solver.add_strategy("little-killers", move |grid| {
+                little_killers(grid, &little_killer_clues)
+            });
+        }
+        if !x_sum_clues.is_empty() {
+            solver.add_strategy("x-sums", move |grid| x_sums(grid, &x_sum_clues));
+        }
+        if !skyscraper_clues.is_empty() {
+            solver.add_strategy("skyscrapers", move |grid| {
+                skyscrapers(grid, &skyscraper_clues)
+            });
+        }
+        if !parity_overlays.is_empty() {
+            solver.add_strategy("parity", move |grid| parity(grid, &parity_overlays));
+        }
+        if !inequality_overlays.is_empty() {
+            solver.add_strategy("inequalities", move |grid| {
+                inequalities(grid, &inequality_overlays)
+            });
+        }
+        if !quadruple_overlays.is_empty() {
+            solver.add_strategy("quadruples", move |grid| {
+                quadruples(grid, &quadruple_overlays)
+            });
+        }
+        if !dots.is_empty() || all_dots {
+            solver.add_strategy("kropki", move |grid| kropki(grid, &dots, all_dots));
+        }
+        if !markers.is_empty() || all_xv {
+            solver.add_strategy("xv", move |grid| xv(grid, &markers, all_xv));
+        }
+        solver.add_strategy("hidden-singles", hidden_singles);
+        solver.add_strategy("naked-tuples", naked_tuples);
+        solver.add_strategy("hidden-tuples", hidden_tuples);
+        solver.add_strategy("pointing-tuples", pointing_tuples);
+        solver
+    }
+}
+impl TryFrom<PuzzleFile> for Puzzle {
+    type Error = String;
+
+    fn try_from(file: PuzzleFile) -> Result<Self, Self::Error> {
+        if file.size != 9 {
+            return Err(format!(
+                "only 9x9 puzzles are supported, found size {}",
+                file.size
+            ));
+        }
+        let mut grid = parse_givens(&file.givens).map_err(|e| e.to_string())?;
+
+        if let Some(regions) = file.regions {
+            let regions = regions
+                .split_whitespace()
+                .collect::<String>()
+                .chars()
+                .map(|c| match c.to_digit(10) {
+                    Some(d @ 1..=9) => Ok(d as usize - 1),
+                    _ => Err(format!("invalid region `{}`, expected 1-9", c)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            grid.regions = Some(region_map(&regions)?);
+        }
+
+        check_givens(&grid).map_err(|e| e.to_string())?;
+
+        for (cell, digits) in &file.pencil_marks {
+            let (r, c) = parse_cell(cell)?;
+            if let Cell::Solved(_) = grid[r][c] {
+                return Err(format!("pencil marks for {} but it is a given", cell));
+            }
+            grid[r][c].restrict(&parse_digits(digits)?);
+            if grid[r][c].candidates().is_empty() {
+                return Err(format!("pencil marks for {} leave it no candidates", cell));
+            }
+        }
+
+        Ok(Puzzle {
+            grid,
+            constraints: file.constraints,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+
+    use crate::{
+        grid::Cell,
+        puzzle::{Puzzle, STRATEGIES},
+    };
+
+    const TOML: &str = r#"
+givens = """
+53..7....
+6..195...
+.98....6.
+8...6...3
+4..8.3..1
+7...2...6
+.6....28.
+...419..5
+....8..79
+"""
+pencil_marks = { r1c3 = "124" }
+constraints = [
+    { type = "antiknight" },
+    { type = "antiqueen", digits = "9" },
+    { type = "diagonal" },
+    { type = "cage", spec = "r1c3,r1c4=10" },
+]
+"#;
+
+    #[test]
+    fn test_toml() {
+        let puzzle = Puzzle::from_toml(TOML).unwrap();
+
+        assert_eq!(puzzle.grid[0][0], Cell::Solved(4));
+        assert_eq!(puzzle.grid[0][2].candidates(), vec![0, 1, 3]);
+        assert_eq!(puzzle.constraints.len(), 4);
+        assert_eq!(puzzle.extra_houses().len(), 2);
+    }
+
+    #[test]
+    fn test_json() {
+        let puzzle = Puzzle::from_json(
+            r#"{
+                "givens": ".................................................................................",
+                "regions": "112122333111222333111222333444555666444555666444555666777888999777888999777888999",
+                "constraints": [{ "type": "thermo", "spec": "r1c1,r1c2" }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(puzzle.grid.region((0, 2)), 1);
+        assert!(puzzle.grid.sees((0, 3), (2, 0)));
+        assert!(!puzzle.grid.sees((0, 2), (2, 0)));
+    }
+
+    #[test]
+    fn test_strategies() {
+        // One of every kind of constraint, so the solver registers every strategy
+        let puzzle = Puzzle::from_toml(&format!(
+            r#"
+            givens = "{}"
+            constraints = [
+                {{ type = "antiking" }},
+                {{ type = "antiknight" }},
+                {{ type = "antiqueen" }},
+                {{ type = "antibishop" }},
+                {{ type = "antirook-diagonal" }},
+                {{ type = "nonconsecutive" }},
+                {{ type = "cage", spec = "r1c1,r1c2=10" }},
+                {{ type = "thermo", spec = "r2c1,r2c2" }},
+                {{ type = "arrow", spec = "r3c1=r3c2" }},
+                {{ type = "german-whispers", spec = "r4c1,r4c2" }},
+                {{ type = "dutch-whispers", spec = "r5c1,r5c2" }},
+                {{ type = "renban", spec = "r6c1,r6c2" }},
+                {{ type = "palindrome", spec = "r7c1,r7c2" }},
+                {{ type = "region-sum", spec = "r8c3,r8c4" }},
+                {{ type = "zipper", spec = "r9c1,r9c2,r9c3" }},
+                {{ type = "between", spec = "r1c5,r1c6,r1c7" }},
+                {{ type = "little-killer", spec = "r0c3:se=15" }},
+                {{ type = "x-sum", spec = "r0c3=15" }},
+                {{ type = "skyscraper", spec = "r10c3=4" }},
+                {{ type = "overlay", spec = "even:r2c5" }},
+                {{ type = "overlay", spec = "gt:r3c5,r3c6" }},
+                {{ type = "overlay", spec = "quad:r4c5=12" }},
+                {{ type = "all-dots" }},
+                {{ type = "all-xv" }},
+            ]
+            "#,
+            ".".repeat(81)
+        ))
+        .unwrap();
+        assert_eq!(puzzle.solver().names().collect_vec(), STRATEGIES);
+    }
+
+    #[test]
+    fn test_invalid_puzzles() {
+        let bad_regions = format!(
+            r#"{{ "givens": "{}", "regions": "111222333111222333111222333444555666444555666444555666777888999777888999777888998" }}"#,
+            ".".repeat(81)
+        );
+        assert!(Puzzle::from_json(&bad_regions).is_err());
+        assert!(Puzzle::from_toml("size = 6\ngivens = \"\"").is_err());
+        assert!(Puzzle::from_toml("givens = \"123\"").is_err());
+        let missing_spec = format!(
+            "givens = \"{}\"\nconstraints = [{{ type = \"cage\" }}]",
+            ".".repeat(81)
+        );
+        assert!(Puzzle::from_toml(&missing_spec).is_err());
+        // Pencil marks can't overwrite a given or rule out every digit
+        assert!(Puzzle::from_toml(&TOML.replace("r1c3 = \"124\"", "r1c1 = \"5\"")).is_err());
+        assert!(Puzzle::from_toml(&TOML.replace("\"124\"", "\"\"")).is_err());
+    }
+}
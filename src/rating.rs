@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{
+    grid::Grid,
+    report::{self, Status},
+    solver::Solver,
+};
+
+/// How hard a puzzle is, by the hardest technique it needs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Difficulty {
+    /// Singles and basic elimination only
+    Easy,
+    /// Also pointing tuples
+    Medium,
+    /// Naked or hidden tuples, or logic specific to a variant
+    Hard,
+    /// Can't be finished without guessing
+    Fiendish,
+}
+impl Difficulty {
+    /// The tier of a strategy. Tuples and every variant strategy share the hard tier
+    pub fn of_strategy(strategy: &str) -> Difficulty {
+        match strategy {
+            "naked-singles" | "hidden-singles" | "basic-elimination" => Difficulty::Easy,
+            "pointing-tuples" => Difficulty::Medium,
+            _ => Difficulty::Hard,
+        }
+    }
+    /// Points added to the score each time a strategy of this difficulty is applied
+    fn weight(self) -> usize {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Medium => 3,
+            Difficulty::Hard => 5,
+            Difficulty::Fiendish => 20,
+        }
+    }
+}
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Fiendish => "fiendish",
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Rating {
+    pub status: Status,
+    pub difficulty: Difficulty,
+    /// The weighted count of strategy applications and guesses
+    pub score: usize,
+    pub steps: usize,
+    /// How many times each strategy was applied
+    pub strategies: BTreeMap<&'static str, usize>,
+    pub guesses: usize,
+}
+impl std::fmt::Display for Rating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Difficulty: {} (score {})", self.difficulty, self.score)?;
+        writeln!(f, "Steps: {}", self.steps)?;
+        for (strategy, count) in &self.strategies {
+            writeln!(f, "  {}: {}", strategy, count)?;
+        }
+        writeln!(f, "Guesses: {}", self.guesses)
+    }
+}
+
+/// Solves a puzzle, keeping track of the strategies used, and guesses to finish it if needed
+pub fn rate(solver: &Solver, grid: &Grid) -> Rating {
+    let mut grid = grid.clone();
+    let mut rating = Rating {
+        status: Status::Solved,
+        difficulty: Difficulty::Easy,
+        score: 0,
+        steps: 0,
+        strategies: BTreeMap::new(),
+        guesses: 0,
+    };
+    while let Some(strategy) = solver.deduce(&mut grid) {
+        let difficulty = Difficulty::of_strategy(strategy);
+        rating.difficulty = rating.difficulty.max(difficulty);
+        rating.score += difficulty.weight();
+        rating.steps += 1;
+        *rating.strategies.entry(strategy).or_default() += 1;
+    }
+    let (status, backtracking) = report::finish(solver, &mut grid, true);
+    rating.status = status;
+    if let Some(backtracking) = backtracking {
+        rating.difficulty = Difficulty::Fiendish;
+        rating.guesses = backtracking.guesses;
+        rating.score += backtracking.guesses * Difficulty::Fiendish.weight();
+    }
+    rating
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        parser::parse_grid,
+        puzzle::Puzzle,
+        rating::{rate, Difficulty},
+        report::Status,
+    };
+
+    #[test]
+    fn test_rate() {
+        let solver = Puzzle {
+            grid: Default::default(),
+            constraints: vec![],
+        }
+        .solver();
+
+        let classic = parse_grid(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let rating = rate(&solver, &classic);
+        assert_eq!(rating.status, Status::Solved);
+        assert_eq!(rating.difficulty, Difficulty::Easy);
+        assert_eq!(rating.guesses, 0);
+        assert_eq!(rating.steps, rating.strategies.values().sum::<usize>());
+
+        let hard = parse_grid(
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..",
+        )
+        .unwrap();
+        let rating = rate(&solver, &hard);
+        assert_eq!(rating.status, Status::Solved);
+        assert_eq!(rating.difficulty, Difficulty::Fiendish);
+        assert!(rating.guesses > 0);
+
+        let empty = parse_grid(&".".repeat(81)).unwrap();
+        assert_eq!(rate(&solver, &empty).status, Status::Multiple);
+    }
+}
@@ -0,0 +1,480 @@
+use std::{
+    fs::read_to_string,
+    io::{self, BufRead, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use itertools::Itertools;
+
+use crate::{
+    chess_strategies::parse_digits,
+    config::StrategyConfig,
+    grid::{parse_cell, Cell, Grid},
+    import::is_link,
+    journal::{Edit, Journal},
+    puzzle::{Constraint, Puzzle, STRATEGIES},
+    solver::Solver,
+    walkthrough::{cell_name, Step},
+};
+
+const HELP: &str = "\
+load SOURCE             load a puzzle from a file, a link or a grid typed on the line
+show                    print the grid with its pencil marks
+step [N]                apply the next N deductions, 1 by default
+apply STRATEGY          apply one strategy by name, e.g. `apply hidden-singles`
+eliminate CELL DIGITS   remove candidates, e.g. `eliminate r3c4 7`
+place CELL DIGIT        fill in a cell other than a given, e.g. `place r1c1 5`
+undo                    undo the last change
+redo                    redo the last change undone
+history                 list the changes made so far, step by step
+rewind N                go back or forward to just after step N, or the start for 0
+diff [N [M]]            show the changes made by step N, or after step N up to step M
+replay FILE             replay a journal written by `solve --journal` onto the puzzle
+backtrack               finish the solve by backtracking
+strategies              list the strategies `step` tries, in order
+quit                    leave";
+
+/// A line typed into the REPL
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Loads a puzzle from a file or link, or a grid typed on the line
+    Load(String),
+    Show,
+    /// Applies up to this many deductions, each by the first strategy to make progress
+    Step(usize),
+    /// Applies one strategy, even one left out by the strategy options
+    Apply(String),
+    Eliminate((usize, usize), [bool; 9]),
+    /// Fills in a cell with a 0-indexed digit
+    Place((usize, usize), usize),
+    Undo,
+    Redo,
+    History,
+    /// Undoes or redoes steps until just this many are applied
+    Rewind(usize),
+    /// Shows the changes made after the first steps up to a later step
+    Diff(Option<usize>, Option<usize>),
+    /// Replaces the journal with one read from a file, replayed from the start of the puzzle
+    Replay(String),
+    Backtrack,
+    Strategies,
+    Help,
+    Quit,
+}
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (command, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let command = command.to_ascii_lowercase();
+        let args = rest.split_whitespace().collect_vec();
+        let usage = || format!("invalid arguments to `{}`, try `help`", command);
+        Ok(match (command.as_str(), args.as_slice()) {
+            ("load", [_, ..]) => Command::Load(rest.trim().to_string()),
+            ("show", []) => Command::Show,
+            ("step", []) => Command::Step(1),
+            ("step", [n]) => Command::Step(n.parse().map_err(|_| usage())?),
+            ("apply", [strategy]) => Command::Apply(strategy.to_string()),
+            ("eliminate", [cell, digits @ ..]) if !digits.is_empty() => {
+                Command::Eliminate(parse_cell(cell)?, parse_digits(&digits.concat())?)
+            }
+            ("place", [cell, digit]) => match digit.parse::<usize>() {
+                Ok(n @ 1..=9) => Command::Place(parse_cell(cell)?, n - 1),
+                _ => return Err(format!("invalid digit `{}`, expected 1-9", digit)),
+            },
+            ("undo", []) => Command::Undo,
+            ("redo", []) => Command::Redo,
+            ("history", []) => Command::History,
+            ("rewind", [n]) => Command::Rewind(n.parse().map_err(|_| usage())?),
+            ("diff", steps) if steps.len() <= 2 => {
+                let steps = steps
+                    .iter()
+                    .map(|n| n.parse().map_err(|_| usage()))
+                    .collect::<Result<Vec<usize>, _>>()?;
+                Command::Diff(steps.first().copied(), steps.get(1).copied())
+            }
+            ("replay", [_, ..]) => Command::Replay(rest.trim().to_string()),
+            ("backtrack", []) => Command::Backtrack,
+            ("strategies", []) => Command::Strategies,
+            ("help" | "?", []) => Command::Help,
+            ("quit" | "exit", []) => Command::Quit,
+            (
+                "load" | "show" | "step" | "apply" | "eliminate" | "place" | "undo" | "redo"
+                | "history" | "rewind" | "diff" | "replay" | "backtrack" | "strategies" | "help"
+                | "?" | "quit" | "exit",
+                _,
+            ) => return Err(usage()),
+            _ => return Err(format!("unknown command `{}`, try `help`", command)),
+        })
+    }
+}
+
+/// An interactive session for stepping through a solve and changing the grid by hand
+pub struct Repl {
+    /// Constraints given as flags, added to every puzzle loaded
+    constraints: Vec<Constraint>,
+    config: StrategyConfig,
+    solver: Solver,
+    /// The puzzle as loaded, whose solved cells `place` won't overwrite and which `replay`
+    /// starts from
+    givens: Grid,
+    grid: Grid,
+    journal: Journal,
+}
+impl Repl {
+    pub fn new(
+        puzzle: Puzzle,
+        constraints: Vec<Constraint>,
+        config: StrategyConfig,
+    ) -> Result<Repl, String> {
+        let mut repl = Repl {
+            constraints,
+            config,
+            solver: Solver::new(),
+            givens: Grid::default(),
+            grid: Grid::default(),
+            journal: Journal::default(),
+        };
+        repl.load(puzzle)?;
+        Ok(repl)
+    }
+    fn load(&mut self, puzzle: Puzzle) -> Result<(), String> {
+        let puzzle = puzzle.with_constraints(self.constraints.clone())?;
+        let mut solver = puzzle.solver();
+        self.config.apply(&mut solver)?;
+        self.solver = solver;
+        self.givens = puzzle.grid.clone();
+        self.grid = puzzle.grid;
+        self.journal = Journal::default();
+        Ok(())
+    }
+    /// Replaces the grid, recording the changes in the journal
+    fn change(&mut self, label: impl Into<String>, grid: Grid) {
+        self.journal.record(label, &self.grid, &grid);
+        self.grid = grid;
+    }
+
+    /// Carries out a command, returning what to print
+    pub fn execute(&mut self, command: Command) -> Result<String, String> {
+        match command {
+            Command::Load(source) => {
+                // Anything that looks like a path is read as one, so a missing file is reported
+                // rather than parsed as a grid
+                let file = !is_link(&source)
+                    && (Path::new(&source).is_file()
+                        || source.contains('/')
+                        || [".toml", ".json", ".txt"]
+                            .iter()
+                            .any(|extension| source.ends_with(extension)));
+                let named = file || is_link(&source);
+                let text = if file {
+                    read_to_string(&source).map_err(|e| format!("{}: {}", source, e))?
+                } else {
+                    source.clone()
+                };
+                self.load(Puzzle::parse(named.then_some(source.as_str()), &text)?)?;
+                Ok(self.grid.to_pencil_marks())
+            }
+            Command::Show => Ok(self.grid.to_pencil_marks()),
+            Command::Step(count) => {
+                let mut lines = vec![];
+                for _ in 0..count {
+                    let mut grid = self.grid.clone();
+                    let Some(strategy) = self.solver.deduce(&mut grid) else {
+                        lines.push(if self.grid.solved() {
+                            "The puzzle is solved".to_string()
+                        } else {
+                            "No logical steps remain".to_string()
+                        });
+                        break;
+                    };
+                    let step = Step::new(strategy, &self.grid, &grid);
+                    lines.push(step.explanation);
+                    self.change(strategy, grid);
+                }
+                Ok(lines.join("\n"))
+            }
+            Command::Apply(name) => {
+                let name = name.to_ascii_lowercase().replace('_', "-");
+                let Some(&strategy) = STRATEGIES.iter().find(|s| **s == name) else {
+                    return Err(format!("unknown strategy `{}`", name));
+                };
+                let mut grid = self.grid.clone();
+                match self.solver.apply(strategy, &mut grid) {
+                    None => Err(format!("{} doesn't apply to this puzzle", strategy)),
+                    Some(false) => Ok(format!("{} found nothing", strategy)),
+                    Some(true) => {
+                        let step = Step::new(strategy, &self.grid, &grid);
+                        self.change(strategy, grid);
+                        Ok(step.explanation)
+                    }
+                }
+            }
+            Command::Eliminate(cell, digits) => {
+                let Cell::Unsolved(candidates) = self.grid[cell.0][cell.1] else {
+                    return Err(format!("{} is already filled in", cell_name(cell)));
+                };
+                let removed = (0..9).filter(|&n| digits[n] && candidates[n]).collect_vec();
+                if removed.is_empty() {
+                    return Ok(format!("{} has none of those candidates", cell_name(cell)));
+                }
+                let mut grid = self.grid.clone();
+                for &n in &removed {
+                    grid[cell.0][cell.1].remove_candidate(n);
+                }
+                let message = format!(
+                    "Removed {} from {}",
+                    removed.iter().map(|n| n + 1).join(""),
+                    cell_name(cell)
+                );
+                self.change(format!("eliminate {}", cell_name(cell)), grid);
+                Ok(message)
+            }
+            Command::Place(cell, n) => {
+                if let Cell::Solved(given) = self.givens[cell.0][cell.1] {
+                    return Err(format!(
+                        "{} is a given {}, which can't be changed",
+                        cell_name(cell),
+                        given + 1
+                    ));
+                }
+                let mut grid = self.grid.clone();
+                grid[cell.0][cell.1] = Cell::Solved(n);
+                self.change(format!("place {} {}", cell_name(cell), n + 1), grid);
+                let mut message = format!("Placed {} in {}", n + 1, cell_name(cell));
+                if self.grid.broken() {
+                    message += ", which breaks the grid";
+                }
+                Ok(message)
+            }
+            Command::Undo => match self.journal.undo(&mut self.grid) {
+                Some(entry) => Ok(format!("Undid {}", entry.label)),
+                None => Err("nothing to undo".to_string()),
+            },
+            Command::Redo => match self.journal.redo(&mut self.grid) {
+                Some(entry) => Ok(format!("Redid {}", entry.label)),
+                None => Err("nothing to redo".to_string()),
+            },
+            Command::History => {
+                if self.journal.entries().is_empty() {
+                    return Ok("No changes yet".to_string());
+                }
+                let position = self.journal.position();
+                Ok(self
+                    .journal
+                    .entries()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        format!(
+                            "{:>3} {} ({} change{}){}",
+                            i + 1,
+                            entry.label,
+                            entry.changes.len(),
+                            if entry.changes.len() == 1 { "" } else { "s" },
+                            if i < position { "" } else { ", undone" }
+                        )
+                    })
+                    .join("\n"))
+            }
+            Command::Rewind(step) => {
+                self.journal.rewind(&mut self.grid, step)?;
+                Ok(self.grid.to_pencil_marks())
+            }
+            Command::Diff(from, to) => {
+                let (from, to) = match (from, to) {
+                    (None, _) => (
+                        self.journal.position().saturating_sub(1),
+                        self.journal.position(),
+                    ),
+                    (Some(step), None) => (step.saturating_sub(1), step),
+                    (Some(from), Some(to)) => (from, to),
+                };
+                if to > self.journal.entries().len() {
+                    return Err(format!("there is no step {}", to));
+                }
+                // One line per cell, with its removed candidates together
+                let lines = self.journal.diff(from, to).group_by(|change| change.cell);
+                Ok(lines
+                    .into_iter()
+                    .map(|(cell, changes)| {
+                        let mut edits = vec![];
+                        let mut removed = String::new();
+                        for change in changes {
+                            match change.edit {
+                                Edit::Remove(n) => removed += &(n + 1).to_string(),
+                                edit => edits.push(edit.to_string()),
+                            }
+                        }
+                        if !removed.is_empty() {
+                            edits.push(format!("removed {}", removed));
+                        }
+                        format!("{}: {}", cell_name(cell), edits.join(", "))
+                    })
+                    .join("\n"))
+            }
+            Command::Replay(path) => {
+                let text = read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+                let mut journal: Journal = text.parse().map_err(|e| format!("{}: {}", path, e))?;
+                // Replays onto a copy, so a journal which doesn't fit leaves the session as it was
+                let mut grid = self.givens.clone();
+                journal.replay(&mut grid)?;
+                self.grid = grid;
+                self.journal = journal;
+                Ok(format!(
+                    "Replayed {} steps, see `history`",
+                    self.journal.position()
+                ))
+            }
+            Command::Backtrack => {
+                if self.grid.solved() {
+                    return Ok("The puzzle is solved".to_string());
+                }
+                let search = self.solver.search(&self.grid, 2);
+                let found = match search.solutions.len() {
+                    0 => "No solutions",
+                    1 => "A unique solution",
+                    _ => "More than one solution, the first",
+                };
+                let summary = format!("{} after {} guesses", found, search.guesses);
+                match search.solutions.into_iter().next() {
+                    Some(solution) => {
+                        self.change("backtrack", solution);
+                        Ok(format!("{}\n{}", summary, self.grid))
+                    }
+                    None => Ok(summary),
+                }
+            }
+            Command::Strategies => Ok(self.solver.names().join(", ")),
+            Command::Help => Ok(HELP.to_string()),
+            Command::Quit => Ok(String::new()),
+        }
+    }
+
+    /// Runs commands line by line until `quit` or the end of the input, skipping blank lines and
+    /// `#` comments. With `prompt`, asks for each line with `> `
+    pub fn run(
+        &mut self,
+        input: impl BufRead,
+        out: &mut impl Write,
+        prompt: bool,
+    ) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            if prompt {
+                write!(out, "> ")?;
+                out.flush()?;
+            }
+            let Some(line) = lines.next() else {
+                return Ok(());
+            };
+            let line = line?;
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let result = match line.parse() {
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => self.execute(command),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(text) => writeln!(out, "{}", text.trim_end())?,
+                Err(e) => writeln!(out, "error: {}", e)?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::StrategyConfig,
+        grid::Cell,
+        parser::parse_grid,
+        puzzle::Puzzle,
+        repl::{Command, Repl},
+    };
+
+    #[test]
+    fn test_repl() {
+        assert_eq!("step".parse(), Ok(Command::Step(1)));
+        assert_eq!("Place r1c1 5".parse(), Ok(Command::Place((0, 0), 4)));
+        assert!("place r1c1 55".parse::<Command>().is_err());
+        assert!("eliminate r1c1".parse::<Command>().is_err());
+        assert!("solve".parse::<Command>().is_err());
+
+        let grid = parse_grid(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let puzzle = Puzzle {
+            grid,
+            constraints: vec![],
+        };
+        let mut repl = Repl::new(puzzle, vec![], StrategyConfig::default()).unwrap();
+
+        let script = "\
+            # comments and blank lines are skipped\n\
+            \n\
+            place r1c1 1\n\
+            load missing/puzzle.txt\n\
+            place r1c3 1\n\
+            undo\n\
+            eliminate r1c3 12\n\
+            apply xwing\n\
+            apply naked_singles\n\
+            step 2\n\
+            history\n\
+            diff 2\n\
+            rewind 1\n\
+            redo\n\
+            backtrack\n\
+            quit\n\
+            place r1c3 1\n";
+        let mut out = vec![];
+        repl.run(script.as_bytes(), &mut out, false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.contains("error: r1c1 is a given 5, which can't be changed\n"));
+        assert!(out.contains("error: missing/puzzle.txt: "));
+        assert!(out.contains("Placed 1 in r1c3\n"));
+        assert!(out.contains("Removed 12 from r1c3\n"));
+        assert!(out.contains("error: unknown strategy `xwing`\n"));
+        assert!(out.contains("naked-singles found nothing\n"));
+        assert!(out.contains("Basic elimination: removes"));
+        assert!(out.contains("  1 eliminate r1c3 (2 changes)\n"));
+        assert!(out.contains("r1c3: removed 356789\n"));
+        assert!(out.contains("Redid basic-elimination\n"));
+        assert!(out.contains("A unique solution after "));
+        assert!(repl.grid.solved());
+        // Nothing runs after `quit`
+        assert_eq!(repl.grid[0][2], Cell::Solved(3));
+
+        // A saved journal replays onto the puzzle as first loaded
+        let path = std::env::temp_dir().join(format!("repl-journal-{}.txt", std::process::id()));
+        std::fs::write(&path, repl.journal.to_string()).unwrap();
+        let steps = repl.journal.entries().len();
+        repl.execute(Command::Rewind(1)).unwrap();
+        let replayed = repl.execute(Command::Replay(path.display().to_string()));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            replayed,
+            Ok(format!("Replayed {} steps, see `history`", steps))
+        );
+        assert!(repl.grid.solved());
+
+        // A journal which doesn't fit the puzzle changes nothing, so undo and redo still work
+        std::fs::write(&path, "step 1: x\n  r1c1: removed 1, was [123456789]\n").unwrap();
+        let replayed = repl.execute(Command::Replay(path.display().to_string()));
+        std::fs::remove_file(&path).unwrap();
+        assert!(replayed.unwrap_err().starts_with("step 1 expects r1c1"));
+        assert!(repl.grid.solved());
+        assert_eq!(repl.journal.position(), steps);
+        assert!(repl.execute(Command::Undo).is_ok());
+        assert!(!repl.grid.solved());
+        assert!(repl.execute(Command::Redo).is_ok());
+        assert!(repl.grid.solved());
+    }
+}
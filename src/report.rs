@@ -0,0 +1,224 @@
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{batch::Outcome, grid::Grid, solver::Solver, walkthrough::Step};
+
+/// How the result of a solve is printed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable log messages only
+    #[default]
+    Text,
+    /// A single JSON document on stdout, alongside the usual logs on stderr
+    Json,
+}
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format `{}`, expected text or json", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    /// Every cell is filled in
+    Solved,
+    /// No logical steps remain and backtracking is disabled
+    Stuck,
+    /// The puzzle has no solution
+    Unsolvable,
+    /// The puzzle has more than one solution
+    Multiple,
+}
+impl Status {
+    /// What the solutions of a search for at most two say about a puzzle
+    pub fn of_solutions(solutions: &[Grid]) -> Status {
+        match solutions.len() {
+            0 => Status::Unsolvable,
+            1 => Status::Solved,
+            _ => Status::Multiple,
+        }
+    }
+}
+
+/// Settles a grid the strategies are done with: solved, broken, stuck if `backtracking` is off,
+/// or else searched for up to two solutions, the first of which replaces `grid`
+pub fn finish(
+    solver: &Solver,
+    grid: &mut Grid,
+    backtracking: bool,
+) -> (Status, Option<BacktrackingStats>) {
+    if grid.broken() {
+        return (Status::Unsolvable, None);
+    }
+    if grid.solved() {
+        return (Status::Solved, None);
+    }
+    if !backtracking {
+        return (Status::Stuck, None);
+    }
+    let start = Instant::now();
+    let search = solver.search(grid, 2);
+    let stats = BacktrackingStats {
+        guesses: search.guesses,
+        seconds: start.elapsed().as_secs_f64(),
+    };
+    let status = Status::of_solutions(&search.solutions);
+    if let Some(solution) = search.solutions.into_iter().next() {
+        *grid = solution;
+    }
+    (status, Some(stats))
+}
+
+/// One successful strategy application
+#[derive(Debug, Serialize)]
+pub struct Deduction {
+    pub strategy: &'static str,
+    /// The cells whose candidates or value changed, as e.g. `r3c4`
+    pub cells: Vec<String>,
+}
+impl Deduction {
+    pub fn new(strategy: &'static str, before: &Grid, after: &Grid) -> Deduction {
+        let cells = (0..9)
+            .cartesian_product(0..9)
+            .filter(|&(r, c)| before[r][c] != after[r][c])
+            .map(|(r, c)| format!("r{}c{}", r + 1, c + 1))
+            .collect();
+        Deduction { strategy, cells }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BacktrackingStats {
+    pub guesses: usize,
+    pub seconds: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Timing {
+    /// Time spent applying strategies, before any backtracking
+    pub logic_seconds: f64,
+    pub total_seconds: f64,
+}
+
+/// Everything known about a finished solve, as printed by `--format json`
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub status: Status,
+    /// The final grid as 81 characters, with `.` for unsolved cells
+    pub grid: String,
+    /// The final grid with its candidates, as written by `--save`
+    pub pencil_marks: String,
+    pub deductions: Vec<Deduction>,
+    /// Missing unless backtracking was needed and enabled
+    pub backtracking: Option<BacktrackingStats>,
+    pub timing: Timing,
+}
+impl Report {
+    pub fn new(status: Status, grid: &Grid, deductions: Vec<Deduction>) -> Report {
+        Report {
+            status,
+            grid: grid.to_line(),
+            pencil_marks: grid.to_pencil_marks(),
+            deductions,
+            backtracking: None,
+            timing: Timing {
+                logic_seconds: 0.0,
+                total_seconds: 0.0,
+            },
+        }
+    }
+    pub fn with_timing(mut self, logic: Duration, total: Duration) -> Report {
+        self.timing = Timing {
+            logic_seconds: logic.as_secs_f64(),
+            total_seconds: total.as_secs_f64(),
+        };
+        self
+    }
+}
+
+/// The next logical step, as printed by `hint`
+#[derive(Debug, Serialize)]
+pub struct Hint {
+    pub strategy: &'static str,
+    pub explanation: String,
+    pub cells: Vec<String>,
+}
+impl Hint {
+    pub fn new(strategy: &'static str, before: &Grid, after: &Grid) -> Hint {
+        Hint {
+            strategy,
+            explanation: Step::new(strategy, before, after).explanation,
+            cells: Deduction::new(strategy, before, after).cells,
+        }
+    }
+}
+
+/// Whether a puzzle has exactly one solution, as printed by `validate`
+#[derive(Debug, Serialize)]
+pub struct Validation {
+    pub status: Status,
+    /// The solution, if it is unique
+    pub solution: Option<String>,
+}
+impl From<Outcome> for Validation {
+    fn from(outcome: Outcome) -> Self {
+        let (status, solution) = match outcome {
+            Outcome::Logical(solution) | Outcome::Backtracking(solution) => {
+                (Status::Solved, Some(solution.to_line()))
+            }
+            Outcome::Unsolvable => (Status::Unsolvable, None),
+            Outcome::Multiple => (Status::Multiple, None),
+        };
+        Validation { status, solution }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Canonical {
+    pub canonical: String,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::Cell,
+        parser::parse_grid,
+        report::{Deduction, Report, Status},
+    };
+
+    #[test]
+    fn test_report() {
+        let before = parse_grid(&".".repeat(81)).unwrap();
+        let mut after = before.clone();
+        after[2][3] = Cell::Solved(4);
+        after[8][0].remove_candidate(4);
+
+        let report = Report::new(
+            Status::Stuck,
+            &after,
+            vec![Deduction::new("hidden-singles", &before, &after)],
+        );
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["status"], "stuck");
+        assert_eq!(json["grid"].as_str().unwrap().chars().nth(21), Some('5'));
+        assert_eq!(json["deductions"][0]["strategy"], "hidden-singles");
+        assert_eq!(
+            json["deductions"][0]["cells"],
+            serde_json::json!(["r3c4", "r9c1"])
+        );
+        assert!(json["backtracking"].is_null());
+    }
+}
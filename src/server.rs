@@ -0,0 +1,298 @@
+use std::{
+    io::Read,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    batch,
+    config::StrategyConfig,
+    generate::{self, Generated, Rng},
+    grid::Grid,
+    puzzle::Puzzle,
+    rating,
+    report::{self, Deduction, Hint, Report, Validation},
+    solver::Solver,
+};
+
+/// Request bodies larger than this are cut short
+const MAX_BODY: u64 = 1 << 20;
+
+/// An HTTP status code and the JSON body sent with it
+type Reply = (u16, Value);
+
+fn error_reply(status: u16, message: impl Into<String>) -> Reply {
+    (status, json!({ "error": message.into() }))
+}
+
+/// Reads a timeout given as a number of seconds
+pub fn parse_timeout(s: &str) -> Result<Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .filter(|timeout| !timeout.is_zero())
+        .ok_or_else(|| {
+            format!(
+                "invalid timeout `{}`, expected a positive number of seconds",
+                s
+            )
+        })
+}
+
+/// The value of `key` in a query string such as `seed=5&backtracking=false`
+fn query_param<T: std::str::FromStr>(query: &str, key: &str) -> Result<Option<T>, Reply> {
+    let Some((_, value)) = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+    else {
+        return Ok(None);
+    };
+    match value.parse() {
+        Ok(value) => Ok(Some(value)),
+        Err(_) => Err(error_reply(400, format!("invalid {} `{}`", key, value))),
+    }
+}
+
+/// Reads a request body: a JSON puzzle file, or a plain or pencil-mark grid
+fn read_puzzle(body: &str) -> Result<Puzzle, String> {
+    let puzzle = if body.trim_start().starts_with('{') {
+        Puzzle::from_json(body)?
+    } else {
+        Puzzle::parse(None, body)?
+    };
+    puzzle.with_constraints(vec![])
+}
+
+fn to_json(value: &impl Serialize) -> Result<Value, Reply> {
+    serde_json::to_value(value).map_err(|e| error_reply(500, e.to_string()))
+}
+
+/// Solves logically, then by backtracking if `backtracking` is set, as `solve --format json`
+fn solve(solver: &Solver, mut grid: Grid, backtracking: bool) -> Report {
+    let start = Instant::now();
+    let mut deductions = vec![];
+    let mut before = grid.clone();
+    while let Some(strategy) = solver.deduce(&mut grid) {
+        deductions.push(Deduction::new(strategy, &before, &grid));
+        before = grid.clone();
+    }
+    let logic_time = start.elapsed();
+
+    let (status, stats) = report::finish(solver, &mut grid, backtracking);
+    let mut report =
+        Report::new(status, &grid, deductions).with_timing(logic_time, start.elapsed());
+    report.backtracking = stats;
+    report
+}
+
+/// The endpoints of the JSON API, independent of the HTTP server
+pub struct Api {
+    config: StrategyConfig,
+    /// How long a request may take before backtracking is stopped and it fails
+    timeout: Duration,
+}
+impl Api {
+    pub fn new(config: StrategyConfig, timeout: Duration) -> Api {
+        Api { config, timeout }
+    }
+
+    /// Answers a request, given its method, its path with any query string, and its body
+    pub fn handle(&self, method: &Method, url: &str, body: &str) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        if !matches!(
+            path,
+            "/solve" | "/hint" | "/rate" | "/generate" | "/validate"
+        ) {
+            return error_reply(404, format!("unknown endpoint {}", path));
+        }
+        match method {
+            Method::Post => match self.answer(path, query, body) {
+                Ok(value) => (200, value),
+                Err(reply) => reply,
+            },
+            // Preflight requests from browsers
+            Method::Options => (204, Value::Null),
+            _ => error_reply(405, format!("{} only accepts POST", path)),
+        }
+    }
+
+    fn answer(&self, path: &str, query: &str, body: &str) -> Result<Value, Reply> {
+        let puzzle = if path == "/generate" && body.trim().is_empty() {
+            Puzzle {
+                grid: Grid::default(),
+                constraints: vec![],
+            }
+        } else {
+            read_puzzle(body).map_err(|e| error_reply(400, e))?
+        };
+        let mut solver = puzzle.solver();
+        self.config
+            .apply(&mut solver)
+            .map_err(|e| error_reply(500, format!("Invalid strategy config: {}", e)))?;
+        solver.set_deadline(Instant::now() + self.timeout);
+
+        let result = match path {
+            "/solve" => {
+                let backtracking = query_param(query, "backtracking")?.unwrap_or(true);
+                to_json(&solve(&solver, puzzle.grid, backtracking))
+            }
+            "/hint" => {
+                let mut grid = puzzle.grid.clone();
+                let hint = solver
+                    .deduce(&mut grid)
+                    .map(|strategy| Hint::new(strategy, &puzzle.grid, &grid));
+                to_json(&hint)
+            }
+            "/rate" => to_json(&rating::rate(&solver, &puzzle.grid)),
+            "/generate" => {
+                let seed = query_param(query, "seed")?.unwrap_or_else(Rng::time_seed);
+                match generate::generate(&solver, &puzzle.grid, &mut Rng::new(seed)) {
+                    Some((grid, solution)) => to_json(&Generated {
+                        puzzle: grid.to_line(),
+                        solution: solution.to_line(),
+                        seed,
+                    }),
+                    None => Err(error_reply(422, "The template has no solutions")),
+                }
+            }
+            _ => to_json(&Validation::from(batch::solve(&solver, puzzle.grid))),
+        };
+        // Searches cut short by the deadline may have missed solutions
+        if solver.expired() {
+            return Err(error_reply(
+                503,
+                format!("Timed out after {:.3?}", self.timeout),
+            ));
+        }
+        result
+    }
+}
+
+fn respond(api: &Api, allow_origin: Option<&str>, mut request: Request) {
+    let mut body = String::new();
+    let (status, value) = match request.as_reader().take(MAX_BODY).read_to_string(&mut body) {
+        Ok(_) => api.handle(request.method(), request.url(), &body),
+        Err(e) => error_reply(400, format!("Failed to read the request: {}", e)),
+    };
+    info!("{} {} -> {}", request.method(), request.url(), status);
+
+    let text = if value.is_null() && status == 204 {
+        String::new()
+    } else {
+        value.to_string()
+    };
+    let mut response = Response::from_string(text)
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+    if let Some(origin) = allow_origin {
+        for (name, value) in [
+            ("Access-Control-Allow-Origin", origin),
+            ("Access-Control-Allow-Methods", "POST, OPTIONS"),
+            ("Access-Control-Allow-Headers", "Content-Type"),
+        ] {
+            if let Ok(header) = Header::from_bytes(name, value) {
+                response.add_header(header);
+            }
+        }
+    }
+    if let Err(e) = request.respond(response) {
+        error!("Failed to send a response: {}", e);
+    }
+}
+
+/// Answers requests on localhost until the process is stopped, on a fixed number of worker
+/// threads. Requests wait in a queue while every worker is busy
+pub fn serve(
+    api: Api,
+    port: u16,
+    workers: usize,
+    allow_origin: Option<String>,
+) -> Result<(), String> {
+    let server = Arc::new(Server::http(("127.0.0.1", port)).map_err(|e| e.to_string())?);
+    info!(
+        "Listening on http://127.0.0.1:{} with {} workers",
+        port, workers
+    );
+    let api = Arc::new(api);
+    let allow_origin = Arc::new(allow_origin);
+    let handles = (0..workers)
+        .map(|_| {
+            let (server, api, allow_origin) = (server.clone(), api.clone(), allow_origin.clone());
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    respond(&api, allow_origin.as_deref(), request);
+                }
+            })
+        })
+        .collect_vec();
+    for handle in handles {
+        handle
+            .join()
+            .map_err(|_| "a worker thread panicked".to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tiny_http::Method;
+
+    use crate::{config::StrategyConfig, server::Api};
+
+    #[test]
+    fn test_api() {
+        let api = Api::new(StrategyConfig::default(), Duration::from_secs(10));
+        let classic =
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+
+        let (status, value) = api.handle(&Method::Post, "/solve", classic);
+        assert_eq!(status, 200);
+        assert_eq!(value["status"], "solved");
+        assert!(!value["grid"].as_str().unwrap().contains('.'));
+
+        assert_eq!(
+            api.handle(&Method::Post, "/hint", r#"{ "givens": "123" }"#)
+                .0,
+            400
+        );
+        let json = format!(r#"{{ "givens": "{}" }}"#, classic);
+        let (status, value) = api.handle(&Method::Post, "/hint", &json);
+        assert_eq!(status, 200);
+        assert!(value["strategy"].is_string());
+
+        let (_, value) = api.handle(&Method::Post, "/validate", &".".repeat(81));
+        assert_eq!(value["status"], "multiple");
+        let (_, value) = api.handle(&Method::Post, "/solve?backtracking=false", &".".repeat(81));
+        assert_eq!(value["status"], "stuck");
+        assert_eq!(
+            api.handle(&Method::Post, "/solve?backtracking=no", classic)
+                .0,
+            400
+        );
+
+        let (_, first) = api.handle(&Method::Post, "/generate?seed=3", "");
+        let (_, second) = api.handle(&Method::Post, "/generate?seed=3", "");
+        assert_eq!(first, second);
+        assert_eq!(first["seed"], 3);
+
+        assert_eq!(api.handle(&Method::Get, "/rate", classic).0, 405);
+        assert_eq!(api.handle(&Method::Post, "/", classic).0, 404);
+
+        // Generating takes many searches, so a tiny timeout cuts it short
+        let api = Api::new(StrategyConfig::default(), Duration::from_nanos(1));
+        assert_eq!(api.handle(&Method::Post, "/generate", "").0, 503);
+        // The deadline stops logical steps as well as backtracking
+        let (status, _) = api.handle(&Method::Post, "/solve?backtracking=false", classic);
+        assert_eq!(status, 503);
+    }
+}
@@ -1,24 +1,105 @@
-use log::{info, trace};
+use std::time::Instant;
+
+use log::trace;
 
 use crate::grid::{Cell, Grid};
 
-type Strategy = fn(&mut Grid) -> bool;
+type Strategy = Box<dyn Fn(&mut Grid) -> bool>;
 
 pub struct Solver {
-    strategies: Vec<Strategy>,
+    strategies: Vec<(&'static str, Strategy)>,
+    /// The strategies used for logical steps, as indices into `strategies`, in order
+    enabled: Vec<usize>,
+    /// When backtracking gives up, leaving searches incomplete
+    deadline: Option<Instant>,
+}
+
+/// The outcome of a backtracking search
+pub struct Search {
+    pub solutions: Vec<Grid>,
+    /// How many digits were tried in total
+    pub guesses: usize,
 }
 impl Solver {
     pub fn new() -> Solver {
-        Solver { strategies: vec![] }
+        Solver {
+            strategies: vec![],
+            enabled: vec![],
+            deadline: None,
+        }
+    }
+    pub fn add_strategy(
+        &mut self,
+        name: &'static str,
+        strategy: impl Fn(&mut Grid) -> bool + 'static,
+    ) {
+        self.enabled.push(self.strategies.len());
+        self.strategies.push((name, Box::new(strategy)));
+    }
+    /// The names of the strategies used for logical steps, in order
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.enabled.iter().map(|&i| self.strategies[i].0)
     }
-    pub fn add_strategy(&mut self, strategy: Strategy) {
-        self.strategies.push(strategy);
+    /// Uses only the named strategies for logical steps, in the given order. Names this solver
+    /// doesn't have are ignored. Backtracking still applies every strategy, as each one
+    /// enforces a rule of the puzzle
+    pub fn set_order(&mut self, names: &[&str]) {
+        self.enabled = names
+            .iter()
+            .filter_map(|name| self.strategies.iter().position(|(n, _)| n == name))
+            .collect();
+    }
+
+    /// Stops logical steps and backtracking once `deadline` has passed. Results found after
+    /// that are incomplete, so callers should check `expired`
+    #[cfg_attr(not(feature = "serve"), allow(dead_code))]
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+    pub fn expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
     }
 
     pub fn step(&self, grid: &mut Grid) -> bool {
-        self.strategies.iter().find(|strat| strat(grid)).is_some()
+        self.deduce(grid).is_some()
+    }
+    /// Applies the first strategy which makes progress, returning its name
+    pub fn deduce(&self, grid: &mut Grid) -> Option<&'static str> {
+        if self.expired() {
+            return None;
+        }
+        self.enabled
+            .iter()
+            .map(|&i| &self.strategies[i])
+            .find(|(_, strat)| strat(grid))
+            .map(|(name, _)| *name)
+    }
+    /// Applies one strategy by name, enabled or not, returning whether it made progress, or
+    /// None if this solver doesn't have it
+    pub fn apply(&self, name: &str, grid: &mut Grid) -> Option<bool> {
+        let (_, strategy) = self.strategies.iter().find(|(n, _)| *n == name)?;
+        Some(strategy(grid))
+    }
+    /// Applies the first of all strategies which makes progress, enabled or not
+    fn propagate(&self, grid: &mut Grid) -> bool {
+        self.strategies.iter().any(|(_, strat)| strat(grid))
+    }
+    /// Finds up to `limit` solutions, guessing in the cell with the fewest candidates and
+    /// stepping after each guess
+    pub fn solutions(&self, grid: &Grid, limit: usize) -> Vec<Grid> {
+        self.search(grid, limit).solutions
+    }
+    /// Like `solutions`, also counting the guesses made along the way
+    pub fn search(&self, grid: &Grid, limit: usize) -> Search {
+        let mut search = Search {
+            solutions: Vec::new(),
+            guesses: 0,
+        };
+        self.search_from(grid, limit, &mut search);
+        search
     }
-    pub fn backtrack(&self, grid: &mut Grid) -> bool {
+    fn search_from(&self, grid: &Grid, limit: usize, search: &mut Search) {
         let target = grid.iter().flatten().enumerate().fold(None, |p, (i, c)| {
             let Cell::Unsolved(ccands) = c else { return p };
             if let Some(pi) = p {
@@ -30,46 +111,46 @@ impl Solver {
                         return Some(i);
                     }
                 }
-                return p;
+                p
             } else {
-                return Some(i);
+                Some(i)
             }
         });
 
-        let Some(i) = target else { return false };
+        let Some(i) = target else { return };
+        if self.expired() {
+            return;
+        }
         let Cell::Unsolved(cands) = grid[i / 9][i % 9] else {
-            return false;
+            return;
         };
 
-        let mut copy = Grid([[Cell::default(); 9]; 9]);
         for cand in cands
             .iter()
             .enumerate()
             .filter_map(|(i, t)| if *t { Some(i) } else { None })
         {
-            copy.copy_from_slice(&(*grid).0);
+            let mut copy = grid.clone();
             copy[i / 9][i % 9] = Cell::Solved(cand);
+            search.guesses += 1;
             trace!("Trying a {} in R{}C{}...", cand + 1, i / 9, i % 9);
             trace!("{}", copy);
-            while !copy.solved() {
-                if self.step(&mut copy) {
-                    trace!("{}", copy);
-                } else if copy.broken() {
-                    trace!("Backtracking failed, backing up");
-                    break;
-                } else if !copy.solved() {
-                    trace!("Backtracking further...");
-                    if self.backtrack(&mut copy) {
-                        return true;
-                    }
-                }
+            // Step until no deductions remain, so that variant strategies also get to check
+            // cells which were only just solved
+            while self.propagate(&mut copy) {
+                trace!("{}", copy);
             }
-            if copy.solved() {
-                info!("Solution found!\n{}", copy);
-                return true;
+            if copy.broken() {
+                trace!("Backtracking failed, backing up");
+            } else if copy.solved() {
+                search.solutions.push(copy);
+            } else {
+                trace!("Backtracking further...");
+                self.search_from(&copy, limit, search);
+            }
+            if search.solutions.len() >= limit {
+                return;
             }
         }
-
-        false
     }
 }
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+
+use crate::grid::Grid;
+
+/// Number of search nodes after which `sum_options` gives up rather than stall the solver
+const SEARCH_LIMIT: usize = 200_000;
+
+pub struct SumOptions {
+    /// Every total reachable by a valid fill of the cells
+    pub sums: BTreeSet<usize>,
+    /// For each cell, the digits that appear in at least one valid fill
+    pub candidates: Vec<[bool; 9]>,
+}
+
+struct Search<'a> {
+    options: Vec<Vec<usize>>,
+    conflicts: Vec<Vec<bool>>,
+    rest_min: Vec<usize>,
+    rest_max: Vec<usize>,
+    targets: &'a BTreeSet<usize>,
+    chosen: Vec<usize>,
+    nodes: usize,
+    found: SumOptions,
+}
+impl Search<'_> {
+    fn run(&mut self, depth: usize, sum: usize) -> bool {
+        self.nodes += 1;
+        if self.nodes > SEARCH_LIMIT {
+            return false;
+        }
+        let (Some(min), Some(max)) = (self.targets.first(), self.targets.last()) else {
+            return true;
+        };
+        if sum + self.rest_min[depth] > *max || sum + self.rest_max[depth] < *min {
+            return true;
+        }
+        if depth == self.options.len() {
+            if self.targets.contains(&sum) {
+                self.found.sums.insert(sum);
+                for (i, n) in self.chosen.iter().enumerate() {
+                    self.found.candidates[i][*n] = true;
+                }
+            }
+            return true;
+        }
+        for n in self.options[depth].clone() {
+            if (0..depth).any(|j| self.conflicts[depth][j] && self.chosen[j] == n) {
+                continue;
+            }
+            self.chosen.push(n);
+            let finished = self.run(depth + 1, sum + n + 1);
+            self.chosen.pop();
+            if !finished {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Enumerates the ways of filling `cells` from their current options so that cells sharing a
+/// house hold distinct digits and the digits total one of `targets`.
+/// Partial sums are pruned against the remaining cells' range, but very loose constraints can
+/// still be too large to search, in which case `None` is returned
+pub fn sum_options(
+    grid: &Grid,
+    cells: &[(usize, usize)],
+    targets: &BTreeSet<usize>,
+) -> Option<SumOptions> {
+    search_sums(grid, cells, targets, |a, b| grid.sees(a, b))
+}
+
+/// As `sum_options`, but no digit may repeat among the cells, as in a killer cage
+pub fn cage_options(
+    grid: &Grid,
+    cells: &[(usize, usize)],
+    targets: &BTreeSet<usize>,
+) -> Option<SumOptions> {
+    search_sums(grid, cells, targets, |a, b| a != b)
+}
+
+fn search_sums(
+    grid: &Grid,
+    cells: &[(usize, usize)],
+    targets: &BTreeSet<usize>,
+    distinct: impl Fn((usize, usize), (usize, usize)) -> bool,
+) -> Option<SumOptions> {
+    // Search the most constrained cells first, then map the results back to the input order
+    let mut order = (0..cells.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| grid[cells[*i].0][cells[*i].1].options().len());
+    let ordered = order.iter().map(|i| cells[*i]).collect::<Vec<_>>();
+
+    let options = ordered
+        .iter()
+        .map(|(r, c)| grid[*r][*c].options())
+        .collect::<Vec<_>>();
+    let conflicts = ordered
+        .iter()
+        .map(|a| ordered.iter().map(|b| distinct(*a, *b)).collect())
+        .collect();
+    let mut rest_min = vec![0; options.len() + 1];
+    let mut rest_max = vec![0; options.len() + 1];
+    for (i, opts) in options.iter().enumerate().rev() {
+        rest_min[i] = rest_min[i + 1] + opts.iter().min().map_or(0, |n| n + 1);
+        rest_max[i] = rest_max[i + 1] + opts.iter().max().map_or(0, |n| n + 1);
+    }
+
+    let mut search = Search {
+        options,
+        conflicts,
+        rest_min,
+        rest_max,
+        targets,
+        chosen: Vec::new(),
+        nodes: 0,
+        found: SumOptions {
+            sums: BTreeSet::new(),
+            candidates: vec![[false; 9]; cells.len()],
+        },
+    };
+    if !search.run(0, 0) {
+        return None;
+    }
+
+    let mut candidates = vec![[false; 9]; cells.len()];
+    for (i, cands) in order.iter().zip(search.found.candidates) {
+        candidates[*i] = cands;
+    }
+    Some(SumOptions {
+        sums: search.found.sums,
+        candidates,
+    })
+}
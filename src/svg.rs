@@ -0,0 +1,455 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use crate::{
+    basic_sudoku::{self, hidden_single_house},
+    grid::{Cell, Grid},
+    houses::windoku,
+    outside_strategies::OutsideClue,
+    overlay_strategies::Overlay,
+    puzzle::Constraint,
+};
+
+/// Side length of a cell
+const CELL: f64 = 60.0;
+/// Space around the grid for outside clues
+const MARGIN: f64 = 60.0;
+
+/// Cells to pick out when explaining a single deduction
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Highlight {
+    /// The cells the deduction is based on, such as a naked pair or the house a hidden single
+    /// is found in. Only the sudoku strategies report one; variant strategies leave it empty,
+    /// as their clues are drawn anyway
+    pub pattern: Vec<(usize, usize)>,
+    /// The cells it fills in
+    pub placements: Vec<(usize, usize)>,
+    /// The candidates it removes, as a cell and a 0-indexed digit
+    pub eliminations: Vec<((usize, usize), usize)>,
+}
+impl Highlight {
+    /// What `strategy` did between two states of a grid, and the pattern it was based on
+    pub fn from_step(strategy: &str, before: &Grid, after: &Grid) -> Highlight {
+        let mut highlight = Highlight::default();
+        for (r, c) in (0..9).cartesian_product(0..9) {
+            match (before[r][c], after[r][c]) {
+                (Cell::Unsolved(_), Cell::Solved(_)) => highlight.placements.push((r, c)),
+                (Cell::Unsolved(_), after) => {
+                    let remaining = after.options();
+                    highlight.eliminations.extend(
+                        before[r][c]
+                            .candidates()
+                            .into_iter()
+                            .filter(|d| !remaining.contains(d))
+                            .map(|d| ((r, c), d)),
+                    );
+                }
+                _ => {}
+            }
+        }
+        highlight.pattern = match strategy {
+            "naked-singles" => highlight.placements.clone(),
+            "hidden-singles" => {
+                let singles = highlight
+                    .eliminations
+                    .iter()
+                    .map(|(cell, _)| *cell)
+                    .unique();
+                singles
+                    .filter_map(|(r, c)| {
+                        let &[n] = after[r][c].candidates().as_slice() else {
+                            return None;
+                        };
+                        hidden_single_house(before, (r, c), n)
+                    })
+                    .flat_map(|house| before.house_cells(house))
+                    .unique()
+                    .collect()
+            }
+            // The solved cells sharing a house with each candidate they removed
+            "basic-elimination" => highlight
+                .eliminations
+                .iter()
+                .flat_map(|&(cell, n)| {
+                    (0..before.house_count())
+                        .map(|i| before.house_cells(i))
+                        .filter(move |cells| cells.contains(&cell))
+                        .flat_map(|cells| cells.to_vec())
+                        .filter(move |&(r, c)| before[r][c] == Cell::Solved(n))
+                })
+                .unique()
+                .collect(),
+            _ => basic_sudoku::patterns(strategy, before)
+                .into_iter()
+                .flat_map(|pattern| pattern.cells)
+                .unique()
+                .collect(),
+        };
+        highlight
+    }
+}
+
+/// Top left corner of a cell
+fn corner((r, c): (usize, usize)) -> (f64, f64) {
+    (MARGIN + c as f64 * CELL, MARGIN + r as f64 * CELL)
+}
+fn center(cell: (usize, usize)) -> (f64, f64) {
+    let (x, y) = corner(cell);
+    (x + CELL / 2.0, y + CELL / 2.0)
+}
+/// The point halfway between two adjacent cells
+fn midpoint([a, b]: [(usize, usize); 2]) -> (f64, f64) {
+    let ((ax, ay), (bx, by)) = (center(a), center(b));
+    ((ax + bx) / 2.0, (ay + by) / 2.0)
+}
+fn points(cells: &[(usize, usize)]) -> String {
+    cells
+        .iter()
+        .map(|&cell| {
+            let (x, y) = center(cell);
+            format!("{},{}", x, y)
+        })
+        .join(" ")
+}
+/// Where an outside clue sits: one step back from its first cell
+fn clue_position(clue: &OutsideClue) -> Option<(f64, f64)> {
+    let [(r0, c0), (r1, c1)] = [*clue.cells.first()?, *clue.cells.get(1)?];
+    let (x, y) = center((r0, c0));
+    let dr = r1 as f64 - r0 as f64;
+    let dc = c1 as f64 - c0 as f64;
+    Some((x - dc * CELL, y - dr * CELL))
+}
+
+fn line(out: &mut String, cells: &[(usize, usize)], color: &str, width: f64) {
+    let _ = writeln!(
+        out,
+        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round" opacity="0.8"/>"#,
+        points(cells),
+        color,
+        width
+    );
+}
+fn circle(out: &mut String, (x, y): (f64, f64), radius: f64, fill: &str, stroke: &str) {
+    let _ = writeln!(
+        out,
+        r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="{}" stroke-width="2"/>"#,
+        x, y, radius, fill, stroke
+    );
+}
+fn text(out: &mut String, (x, y): (f64, f64), size: f64, color: &str, content: &str) {
+    let _ = writeln!(
+        out,
+        r#"<text x="{}" y="{}" font-size="{}" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+        x, y, size, color, content
+    );
+}
+fn rect(out: &mut String, cell: (usize, usize), inset: f64, fill: &str) {
+    let (x, y) = corner(cell);
+    let _ = writeln!(
+        out,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+        x + inset,
+        y + inset,
+        CELL - 2.0 * inset,
+        CELL - 2.0 * inset,
+        fill
+    );
+}
+/// A dashed outline just inside the edge of a group of cells
+fn cage(out: &mut String, cells: &[(usize, usize)], sum: Option<usize>) {
+    let inset = 5.0;
+    for &(r, c) in cells {
+        let (x, y) = corner((r, c));
+        let (x0, y0, x1, y1) = (x + inset, y + inset, x + CELL - inset, y + CELL - inset);
+        let sides = [
+            (r == 0 || !cells.contains(&(r - 1, c)), (x0, y0, x1, y0)),
+            (!cells.contains(&(r + 1, c)), (x0, y1, x1, y1)),
+            (c == 0 || !cells.contains(&(r, c - 1)), (x0, y0, x0, y1)),
+            (!cells.contains(&(r, c + 1)), (x1, y0, x1, y1)),
+        ];
+        for (_, (x0, y0, x1, y1)) in sides.into_iter().filter(|(edge, _)| *edge) {
+            let _ = writeln!(
+                out,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black" stroke-width="1" stroke-dasharray="4,3"/>"#,
+                x0, y0, x1, y1
+            );
+        }
+    }
+    if let (Some(sum), Some(&first)) = (sum, cells.iter().min()) {
+        let (x, y) = corner(first);
+        let _ = writeln!(
+            out,
+            r#"<text x="{}" y="{}" font-size="11" fill="black">{}</text>"#,
+            x + 3.0,
+            y + 12.0,
+            sum
+        );
+    }
+}
+
+fn constraint(out: &mut String, constraint: &Constraint) {
+    match constraint {
+        Constraint::Diagonal => {
+            line(out, &[(0, 0), (8, 8)], "#2196f3", 2.0);
+            line(out, &[(0, 8), (8, 0)], "#2196f3", 2.0);
+        }
+        Constraint::Windoku => {
+            for cell in windoku().into_iter().flatten() {
+                rect(out, cell, 0.0, "#e0e0e0");
+            }
+        }
+        Constraint::Cage { spec } => cage(out, &spec.cells, spec.sum),
+        Constraint::Thermo { spec } => {
+            line(out, &spec.0, "#bdbdbd", CELL * 0.3);
+            circle(out, center(spec.0[0]), CELL * 0.4, "#bdbdbd", "#bdbdbd");
+        }
+        Constraint::Arrow { spec } => {
+            let mut shaft = vec![spec.circle[spec.circle.len() - 1]];
+            shaft.extend(&spec.shaft);
+            let _ = writeln!(
+                out,
+                r##"<polyline points="{}" fill="none" stroke="#9e9e9e" stroke-width="3" marker-end="url(#arrowhead)"/>"##,
+                points(&shaft)
+            );
+            for &cell in &spec.circle {
+                circle(out, center(cell), CELL * 0.4, "white", "#9e9e9e");
+            }
+        }
+        Constraint::GermanWhispers { spec } => line(out, &spec.0, "#4caf50", CELL * 0.15),
+        Constraint::DutchWhispers { spec } => line(out, &spec.0, "#ff9800", CELL * 0.15),
+        Constraint::Renban { spec } => line(out, &spec.0, "#ce93d8", CELL * 0.15),
+        Constraint::Palindrome { spec } => line(out, &spec.0, "#9e9e9e", CELL * 0.15),
+        Constraint::RegionSum { spec } => line(out, &spec.0, "#64b5f6", CELL * 0.15),
+        Constraint::Zipper { spec } => line(out, &spec.0, "#b39ddb", CELL * 0.15),
+        Constraint::Between { spec } => {
+            line(out, &spec.0, "#9e9e9e", CELL * 0.1);
+            for &end in [spec.0.first(), spec.0.last()].iter().flatten() {
+                circle(out, center(*end), CELL * 0.4, "white", "#9e9e9e");
+            }
+        }
+        Constraint::LittleKiller { spec } => {
+            if let Some(position) = clue_position(&spec.0) {
+                text(out, position, 16.0, "black", &spec.0.value.to_string());
+            }
+        }
+        Constraint::XSum { spec } | Constraint::Skyscraper { spec } => {
+            if let Some(position) = clue_position(&spec.0) {
+                text(out, position, 22.0, "black", &spec.0.value.to_string());
+            }
+        }
+        Constraint::Overlay { spec } => match spec {
+            Overlay::Even(cells) => {
+                for &cell in cells {
+                    rect(out, cell, CELL * 0.12, "#d6d6d6");
+                }
+            }
+            Overlay::Odd(cells) => {
+                for &cell in cells {
+                    circle(out, center(cell), CELL * 0.4, "#d6d6d6", "none");
+                }
+            }
+            Overlay::Greater([a, b]) => {
+                let sign = match (a.0.cmp(&b.0), a.1.cmp(&b.1)) {
+                    (_, std::cmp::Ordering::Less) => "&gt;",
+                    (_, std::cmp::Ordering::Greater) => "&lt;",
+                    (std::cmp::Ordering::Less, _) => "∨",
+                    _ => "∧",
+                };
+                text(out, midpoint([*a, *b]), 20.0, "black", sign);
+            }
+            Overlay::Quadruple { cells, digits } => {
+                let (x, y) = corner(cells[3]);
+                circle(out, (x, y), CELL * 0.25, "white", "black");
+                let digits = digits.iter().map(|d| d + 1).join("");
+                text(out, (x, y), 10.0, "black", &digits);
+            }
+        },
+        Constraint::WhiteDot { spec } => circle(out, midpoint(*spec), 7.0, "white", "black"),
+        Constraint::BlackDot { spec } => circle(out, midpoint(*spec), 7.0, "black", "black"),
+        Constraint::XPair { spec } | Constraint::VPair { spec } => {
+            let letter = if matches!(constraint, Constraint::XPair { .. }) {
+                "X"
+            } else {
+                "V"
+            };
+            circle(out, midpoint(*spec), 9.0, "white", "none");
+            text(out, midpoint(*spec), 16.0, "black", letter);
+        }
+        // Rules without clues in the grid
+        _ => {}
+    }
+}
+
+/// Draws a grid as an SVG image: the givens in black and other solved digits in blue,
+/// pencil marks for unsolved cells, box or jigsaw borders, the clues of any constraints, and
+/// optionally one deduction's pattern and eliminations
+pub fn render(
+    grid: &Grid,
+    givens: &Grid,
+    constraints: &[Constraint],
+    highlight: Option<&Highlight>,
+) -> String {
+    let size = 9.0 * CELL + 2.0 * MARGIN;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}" font-family="sans-serif">"#,
+    );
+    out += r##"<defs><marker id="arrowhead" markerWidth="8" markerHeight="8" refX="7" refY="4" orient="auto"><path d="M1,1 L7,4 L1,7" fill="none" stroke="#9e9e9e" stroke-width="1.5"/></marker></defs>"##;
+    out += "\n";
+    let _ = writeln!(
+        out,
+        r#"<rect width="{size}" height="{size}" fill="white"/>"#
+    );
+
+    if let Some(highlight) = highlight {
+        for &cell in &highlight.pattern {
+            rect(&mut out, cell, 0.0, "#fff59d");
+        }
+        for &cell in &highlight.placements {
+            rect(&mut out, cell, 0.0, "#c8e6c9");
+        }
+        for &cell in highlight.eliminations.iter().map(|(cell, _)| cell).unique() {
+            rect(&mut out, cell, 0.0, "#ffcdd2");
+        }
+    }
+    for constraint_ in constraints {
+        constraint(&mut out, constraint_);
+    }
+
+    // Thin lines between every cell, then thick ones wherever the region changes
+    for i in 0..=9 {
+        let offset = MARGIN + i as f64 * CELL;
+        let end = MARGIN + 9.0 * CELL;
+        let _ = writeln!(
+            out,
+            r##"<path d="M{MARGIN},{offset} H{end} M{offset},{MARGIN} V{end}" stroke="#9e9e9e" stroke-width="1"/>"##,
+        );
+    }
+    let mut borders = String::new();
+    for (r, c) in (0..9).cartesian_product(0..9) {
+        let (x, y) = corner((r, c));
+        if c < 8 && grid.region((r, c)) != grid.region((r, c + 1)) {
+            let _ = write!(borders, "M{},{} V{} ", x + CELL, y, y + CELL);
+        }
+        if r < 8 && grid.region((r, c)) != grid.region((r + 1, c)) {
+            let _ = write!(borders, "M{},{} H{} ", x, y + CELL, x + CELL);
+        }
+    }
+    let _ = writeln!(
+        out,
+        r#"<path d="{}" stroke="black" stroke-width="3" stroke-linecap="square"/>"#,
+        borders.trim_end()
+    );
+    let _ = writeln!(
+        out,
+        r#"<rect x="{MARGIN}" y="{MARGIN}" width="{0}" height="{0}" fill="none" stroke="black" stroke-width="4"/>"#,
+        9.0 * CELL
+    );
+
+    for (r, c) in (0..9).cartesian_product(0..9) {
+        match grid[r][c] {
+            Cell::Solved(n) => {
+                let color = if givens[r][c] == Cell::Solved(n) {
+                    "black"
+                } else {
+                    "#1565c0"
+                };
+                text(
+                    &mut out,
+                    center((r, c)),
+                    CELL * 0.6,
+                    color,
+                    &(n + 1).to_string(),
+                );
+            }
+            Cell::Unsolved(cands) => {
+                let (x, y) = corner((r, c));
+                for d in (0..9).filter(|d| cands[*d]) {
+                    let eliminated = highlight
+                        .is_some_and(|highlight| highlight.eliminations.contains(&((r, c), d)));
+                    let position = (
+                        x + (d % 3) as f64 * CELL / 3.0 + CELL / 6.0,
+                        y + (d / 3) as f64 * CELL / 3.0 + CELL / 6.0,
+                    );
+                    let color = if eliminated { "#d32f2f" } else { "#757575" };
+                    text(&mut out, position, CELL * 0.22, color, &(d + 1).to_string());
+                }
+            }
+        }
+    }
+    out += "</svg>\n";
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        basic_sudoku::naked_tuples,
+        grid::Cell,
+        parser::parse_grid,
+        puzzle::Puzzle,
+        svg::{render, Highlight},
+    };
+
+    #[test]
+    fn test_render() {
+        let puzzle = Puzzle::from_toml(
+            r#"
+            givens = "5................................................................................"
+            constraints = [
+                { type = "cage", spec = "r2c2,r2c3=3" },
+                { type = "thermo", spec = "r5c5,r5c6,r5c7" },
+                { type = "white-dot", spec = "r9c1,r9c2" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let givens = puzzle.grid.clone();
+        let mut grid = givens.clone();
+        grid[0][1] = Cell::Solved(2);
+        grid[0][2].remove_candidate(2);
+
+        let highlight = Highlight::from_step("naked-singles", &givens, &grid);
+        assert_eq!(highlight.pattern, vec![(0, 1)]);
+        assert_eq!(highlight.placements, vec![(0, 1)]);
+        assert_eq!(highlight.eliminations, vec![((0, 2), 2)]);
+
+        // A naked pair in row 1 is the pattern for the 1 and 2 it removes from r1c9
+        let mut pair = parse_grid(&".".repeat(81)).unwrap();
+        for c in [0, 4] {
+            pair[0][c] =
+                Cell::Unsolved([true, true, false, false, false, false, false, false, false]);
+        }
+        let mut after = pair.clone();
+        naked_tuples(&mut after);
+        let tuple = Highlight::from_step("naked-tuples", &pair, &after);
+        assert_eq!(tuple.pattern, vec![(0, 0), (0, 4)]);
+        assert!(tuple.placements.is_empty());
+        assert!(tuple.eliminations.contains(&((0, 8), 1)));
+
+        let svg = render(&grid, &givens, &puzzle.constraints, Some(&highlight));
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        // The given 5 is black and the solved 3 blue
+        assert!(svg.contains(r#"fill="black" text-anchor="middle" dominant-baseline="central">5<"#));
+        assert!(
+            svg.contains(r##"fill="#1565c0" text-anchor="middle" dominant-baseline="central">3<"##)
+        );
+        assert!(svg.contains("stroke-dasharray"));
+        assert!(svg.contains(">3</text>"));
+        assert!(svg.contains("#fff59d"));
+        // Eliminated candidates are still shown, in red
+        assert!(!svg.contains("#d32f2f"));
+        let before = render(&givens, &givens, &[], Some(&highlight));
+        assert!(before.contains("#d32f2f"));
+
+        // Jigsaw regions move the thick borders
+        let mut jigsaw = parse_grid(&".".repeat(81)).unwrap();
+        let boxes = render(&jigsaw, &jigsaw, &[], None);
+        jigsaw.regions = Some(std::array::from_fn(|r| {
+            std::array::from_fn(|c| (c + r) % 9)
+        }));
+        assert_ne!(render(&jigsaw, &jigsaw, &[], None), boxes);
+    }
+}
@@ -0,0 +1,571 @@
+use std::{
+    io::{self, Write},
+    panic,
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{
+        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
+    terminal::{self, ClearType},
+};
+use itertools::Itertools;
+
+use crate::{
+    grid::{Cell, Grid},
+    houses::{diagonals, windoku},
+    journal::{Change, Journal},
+    overlay_strategies::Overlay,
+    puzzle::Constraint,
+    solver::Solver,
+    walkthrough::{cell_name, Step},
+};
+
+/// Lines taken by the grid with pencil marks and the status lines below it
+const FULL_HEIGHT: u16 = 31 + 5;
+
+/// What a key press asks for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Move(isize, isize),
+    /// Places a 0-indexed digit, or toggles it as a pencil mark in pencil mode
+    Digit(usize),
+    Clear,
+    TogglePencil,
+    /// Switches between showing pencil marks in the grid and one line per row
+    ToggleView,
+    /// Shows the next logical step, or applies it if it is already shown
+    Hint,
+    Undo,
+    Redo,
+    /// Marks entries which disagree with the solution
+    Check,
+    Quit,
+}
+impl Action {
+    fn from_key(key: KeyEvent) -> Option<Action> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        Some(match key.code {
+            KeyCode::Up => Action::Move(-1, 0),
+            KeyCode::Down => Action::Move(1, 0),
+            KeyCode::Left => Action::Move(0, -1),
+            KeyCode::Right => Action::Move(0, 1),
+            KeyCode::Char('c') if ctrl => Action::Quit,
+            KeyCode::Char('z') if ctrl => Action::Undo,
+            KeyCode::Char('y') if ctrl => Action::Redo,
+            KeyCode::Char(c @ '1'..='9') => Action::Digit(c as usize - '1' as usize),
+            KeyCode::Char('0') | KeyCode::Backspace | KeyCode::Delete => Action::Clear,
+            KeyCode::Char('p') | KeyCode::Char(' ') => Action::TogglePencil,
+            KeyCode::Char('v') => Action::ToggleView,
+            KeyCode::Char('h') | KeyCode::Char('?') => Action::Hint,
+            KeyCode::Char('u') => Action::Undo,
+            KeyCode::Char('r') => Action::Redo,
+            KeyCode::Char('c') => Action::Check,
+            KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+            _ => return None,
+        })
+    }
+}
+
+/// The name and cells of each variant clue, to shade them and name those under the cursor
+fn overlays(constraints: &[Constraint]) -> Vec<(String, Vec<(usize, usize)>)> {
+    let mut overlays = vec![];
+    for constraint in constraints {
+        let (name, cells) = match constraint {
+            Constraint::Diagonal => {
+                overlays.extend(
+                    diagonals()
+                        .into_iter()
+                        .map(|d| ("diagonal".to_string(), d.to_vec())),
+                );
+                continue;
+            }
+            Constraint::Windoku => {
+                overlays.extend(
+                    windoku()
+                        .into_iter()
+                        .map(|w| ("window".to_string(), w.to_vec())),
+                );
+                continue;
+            }
+            Constraint::Cage { spec } => (
+                spec.sum
+                    .map_or("cage".to_string(), |sum| format!("cage {}", sum)),
+                spec.cells.clone(),
+            ),
+            Constraint::Thermo { spec } => ("thermometer".to_string(), spec.0.clone()),
+            Constraint::Arrow { spec } => (
+                "arrow".to_string(),
+                spec.circle.iter().chain(&spec.shaft).copied().collect(),
+            ),
+            Constraint::GermanWhispers { spec } => ("German whispers".to_string(), spec.0.clone()),
+            Constraint::DutchWhispers { spec } => ("Dutch whispers".to_string(), spec.0.clone()),
+            Constraint::Renban { spec } => ("renban".to_string(), spec.0.clone()),
+            Constraint::Palindrome { spec } => ("palindrome".to_string(), spec.0.clone()),
+            Constraint::RegionSum { spec } => ("region sum line".to_string(), spec.0.clone()),
+            Constraint::Zipper { spec } => ("zipper".to_string(), spec.0.clone()),
+            Constraint::Between { spec } => ("between line".to_string(), spec.0.clone()),
+            Constraint::LittleKiller { spec } => (
+                format!("little killer {}", spec.0.value),
+                spec.0.cells.clone(),
+            ),
+            Constraint::XSum { spec } => (format!("x-sum {}", spec.0.value), spec.0.cells.clone()),
+            Constraint::Skyscraper { spec } => {
+                (format!("skyscraper {}", spec.0.value), spec.0.cells.clone())
+            }
+            Constraint::Overlay { spec } => match spec {
+                Overlay::Even(cells) => ("even".to_string(), cells.clone()),
+                Overlay::Odd(cells) => ("odd".to_string(), cells.clone()),
+                Overlay::Greater([a, b]) => (
+                    format!("{} > {}", cell_name(*a), cell_name(*b)),
+                    vec![*a, *b],
+                ),
+                Overlay::Quadruple { cells, digits } => (
+                    format!("quadruple {}", digits.iter().map(|d| d + 1).join("")),
+                    cells.to_vec(),
+                ),
+            },
+            Constraint::WhiteDot { spec } => ("white dot".to_string(), spec.to_vec()),
+            Constraint::BlackDot { spec } => ("black dot".to_string(), spec.to_vec()),
+            Constraint::XPair { spec } => ("X".to_string(), spec.to_vec()),
+            Constraint::VPair { spec } => ("V".to_string(), spec.to_vec()),
+            // Rules without clues in the grid
+            _ => continue,
+        };
+        overlays.push((name, cells));
+    }
+    overlays
+}
+
+/// A puzzle being solved by hand
+pub struct App<'a> {
+    solver: &'a Solver,
+    pub grid: Grid,
+    givens: Grid,
+    /// The unique solution, if there is one, to check entries against
+    solution: Option<Grid>,
+    overlays: Vec<(String, Vec<(usize, usize)>)>,
+    pub cursor: (usize, usize),
+    pencil: bool,
+    compact: bool,
+    journal: Journal,
+    /// The next logical step, once shown, until the grid changes
+    hint: Option<Step>,
+    /// The cells found wrong by the last check, until the grid changes
+    mistakes: Vec<(usize, usize)>,
+    message: String,
+}
+impl<'a> App<'a> {
+    pub fn new(solver: &'a Solver, grid: Grid, constraints: &[Constraint]) -> App<'a> {
+        let mut solutions = solver.solutions(&grid, 2);
+        let solution = (solutions.len() == 1).then(|| solutions.remove(0));
+        App {
+            solver,
+            givens: grid.clone(),
+            grid,
+            solution,
+            overlays: overlays(constraints),
+            cursor: (0, 0),
+            pencil: false,
+            compact: false,
+            journal: Journal::default(),
+            hint: None,
+            mistakes: vec![],
+            message: "Press h for a hint, or q to quit".to_string(),
+        }
+    }
+
+    /// Carries out an action, returning false once the player quits
+    pub fn apply(&mut self, action: Action) -> bool {
+        let (r, c) = self.cursor;
+        let given = matches!(self.givens[r][c], Cell::Solved(_));
+        match action {
+            Action::Move(dr, dc) => {
+                self.cursor = (
+                    (r as isize + dr).rem_euclid(9) as usize,
+                    (c as isize + dc).rem_euclid(9) as usize,
+                )
+            }
+            Action::Digit(_) | Action::Clear if given => {
+                self.message = format!("{} is a given", cell_name(self.cursor))
+            }
+            Action::Digit(n) if self.pencil => match self.grid[r][c] {
+                Cell::Unsolved(candidates) => {
+                    // A cell with every candidate has no marks yet, so the first digit
+                    // pressed is its only mark, and removing the last mark clears it again
+                    let mut marks = if candidates == [true; 9] {
+                        [false; 9]
+                    } else {
+                        candidates
+                    };
+                    marks[n] = !marks[n];
+                    if marks == [false; 9] {
+                        marks = [true; 9];
+                    }
+                    self.set(Cell::Unsolved(marks));
+                }
+                Cell::Solved(_) => {
+                    self.message = format!("{} is already filled in", cell_name(self.cursor))
+                }
+            },
+            Action::Digit(n) => self.set(Cell::Solved(n)),
+            Action::Clear => self.set(Cell::Unsolved([true; 9])),
+            Action::TogglePencil => self.pencil = !self.pencil,
+            Action::ToggleView => self.compact = !self.compact,
+            Action::Hint => self.hint(),
+            Action::Undo => match self.journal.undo(&mut self.grid) {
+                Some(entry) => {
+                    let message = format!("Undid {}", entry.label);
+                    self.changed();
+                    self.message = message;
+                }
+                None => self.message = "Nothing to undo".to_string(),
+            },
+            Action::Redo => match self.journal.redo(&mut self.grid) {
+                Some(entry) => {
+                    let message = format!("Redid {}", entry.label);
+                    self.changed();
+                    self.message = message;
+                }
+                None => self.message = "Nothing to redo".to_string(),
+            },
+            Action::Check => self.check(),
+            Action::Quit => return false,
+        }
+        true
+    }
+
+    /// Changes the cell under the cursor
+    fn set(&mut self, cell: Cell) {
+        let (r, c) = self.cursor;
+        let mut grid = self.grid.clone();
+        grid[r][c] = cell;
+        let label = Change::diff(&self.grid, &grid).iter().join(", ");
+        if !label.is_empty() {
+            self.edit(label, grid);
+        }
+    }
+    /// Replaces the grid, recording the changes in the journal
+    fn edit(&mut self, label: impl Into<String>, grid: Grid) {
+        self.journal.record(label, &self.grid, &grid);
+        self.grid = grid;
+        self.changed();
+    }
+    fn changed(&mut self) {
+        self.hint = None;
+        self.mistakes.clear();
+        self.message.clear();
+        if self.grid.solved() && !self.grid.broken() {
+            self.message = "Solved!".to_string();
+        }
+    }
+
+    fn hint(&mut self) {
+        if let Some(step) = self.hint.take() {
+            self.edit(step.strategy, step.grid);
+            if self.message.is_empty() {
+                self.message = format!("Applied {}", step.strategy.replace('-', " "));
+            }
+            return;
+        }
+        let mut grid = self.grid.clone();
+        self.message = match self.solver.deduce(&mut grid) {
+            Some(strategy) => {
+                let step = Step::new(strategy, &self.grid, &grid);
+                let message = format!("{} (h again to apply it)", step.explanation);
+                self.hint = Some(step);
+                message
+            }
+            None if self.grid.solved() => "The puzzle is already solved".to_string(),
+            None => "No logical steps remain".to_string(),
+        };
+    }
+
+    fn check(&mut self) {
+        let Some(solution) = &self.solution else {
+            self.message = "The puzzle has no unique solution to check against".to_string();
+            return;
+        };
+        self.mistakes = (0..9)
+            .cartesian_product(0..9)
+            .filter(|&(r, c)| {
+                let Cell::Solved(answer) = solution[r][c] else {
+                    return false;
+                };
+                match self.grid[r][c] {
+                    Cell::Solved(n) => n != answer,
+                    Cell::Unsolved(candidates) => !candidates[answer],
+                }
+            })
+            .collect();
+        self.message = match self.mistakes.len() {
+            0 => "No mistakes so far".to_string(),
+            1 => "1 mistake, marked in red".to_string(),
+            n => format!("{} mistakes, marked in red", n),
+        };
+    }
+
+    /// Whether a solved cell repeats a digit in a cell it sees
+    fn conflicts(&self, (r, c): (usize, usize)) -> bool {
+        let Cell::Solved(n) = self.grid[r][c] else {
+            return false;
+        };
+        (0..9).cartesian_product(0..9).any(|other| {
+            self.grid[other.0][other.1] == Cell::Solved(n) && self.grid.sees((r, c), other)
+        })
+    }
+
+    fn background(&self, cell: (usize, usize)) -> Color {
+        let on =
+            |cells: &Vec<(usize, usize)>| cells.contains(&cell) && cells.contains(&self.cursor);
+        if let Some(step) = &self.hint {
+            if step.highlight.pattern.contains(&cell) {
+                return Color::DarkYellow;
+            }
+            if step.highlight.placements.contains(&cell) {
+                return Color::DarkGreen;
+            }
+            if step.highlight.eliminations.iter().any(|(c, _)| *c == cell) {
+                return Color::DarkMagenta;
+            }
+        }
+        if self.overlays.iter().any(|(_, cells)| on(cells)) {
+            Color::AnsiValue(24)
+        } else if self.overlays.iter().any(|(_, cells)| cells.contains(&cell)) {
+            Color::AnsiValue(237)
+        } else {
+            Color::Reset
+        }
+    }
+
+    /// Writes one line of one cell: its digit, or three of its pencil marks
+    fn draw_cell(
+        &self,
+        out: &mut impl Write,
+        (r, c): (usize, usize),
+        line: usize,
+    ) -> io::Result<()> {
+        queue!(out, SetBackgroundColor(self.background((r, c))))?;
+        if (r, c) == self.cursor {
+            queue!(out, SetAttribute(Attribute::Reverse))?;
+        }
+        let wrong = self.mistakes.contains(&(r, c)) || self.conflicts((r, c));
+        match self.grid[r][c] {
+            Cell::Solved(n) if line == 1 || self.compact => {
+                let color = if wrong {
+                    Color::Red
+                } else if self.givens[r][c] == Cell::Solved(n) {
+                    Color::White
+                } else {
+                    Color::Cyan
+                };
+                queue!(
+                    out,
+                    SetForegroundColor(color),
+                    Print(format!(" {} ", n + 1))
+                )?;
+            }
+            Cell::Unsolved(_) if self.compact => {
+                let color = if wrong { Color::Red } else { Color::DarkGrey };
+                queue!(out, SetForegroundColor(color), Print(" · "))?;
+            }
+            Cell::Unsolved(candidates) if candidates != [true; 9] => {
+                for (n, &candidate) in candidates.iter().enumerate().skip(line * 3).take(3) {
+                    let eliminated = self
+                        .hint
+                        .as_ref()
+                        .is_some_and(|step| step.highlight.eliminations.contains(&((r, c), n)));
+                    let color = if eliminated {
+                        Color::Red
+                    } else if wrong {
+                        Color::DarkRed
+                    } else {
+                        Color::Grey
+                    };
+                    let mark = if candidate {
+                        char::from(b'1' + n as u8)
+                    } else {
+                        ' '
+                    };
+                    queue!(out, SetForegroundColor(color), Print(mark))?;
+                }
+            }
+            _ => queue!(out, Print("   "))?,
+        }
+        queue!(out, SetAttribute(Attribute::Reset), ResetColor)
+    }
+
+    pub fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        queue!(out, terminal::Clear(ClearType::All))?;
+        let border = |left: &str, middle: &str, right: &str| {
+            format!("{}{}{}", left, vec!["─".repeat(13); 3].join(middle), right)
+        };
+        let mut y = 0;
+        /// Moves to the start of the next line
+        fn line(out: &mut impl Write, y: &mut u16) -> io::Result<()> {
+            *y += 1;
+            queue!(out, cursor::MoveTo(0, *y - 1))
+        }
+        line(out, &mut y)?;
+        queue!(out, Print(border("┌", "┬", "┐")))?;
+        for r in 0..9 {
+            for sub in 0..if self.compact { 1 } else { 3 } {
+                line(out, &mut y)?;
+                for c in 0..9 {
+                    queue!(out, Print(if c % 3 == 0 { "│ " } else { " " }))?;
+                    self.draw_cell(out, (r, c), sub)?;
+                }
+                queue!(out, Print(" │"))?;
+            }
+            line(out, &mut y)?;
+            match r {
+                8 => queue!(out, Print(border("└", "┴", "┘")))?,
+                2 | 5 => queue!(out, Print(border("├", "┼", "┤")))?,
+                _ => y -= 1,
+            }
+        }
+
+        let (r, c) = self.cursor;
+        let mut status = format!(
+            "{}  {}",
+            cell_name(self.cursor),
+            if self.pencil {
+                "pencil marks"
+            } else {
+                "digits"
+            }
+        );
+        if let (true, Cell::Unsolved(candidates)) = (self.compact, self.grid[r][c]) {
+            let marks = (0..9).filter(|&n| candidates[n]).map(|n| n + 1).join("");
+            status += &format!("  candidates {}", marks);
+        }
+        let clues = self
+            .overlays
+            .iter()
+            .filter(|(_, cells)| cells.contains(&self.cursor))
+            .map(|(name, _)| name)
+            .join(", ");
+        let help = "arrows move  1-9 enter  space pencil  0 clear  h hint  u/r undo/redo  \
+                    c check  v view  q quit";
+        for text in [
+            &status,
+            &clues,
+            &self.message,
+            &String::new(),
+            &help.to_string(),
+        ] {
+            line(out, &mut y)?;
+            queue!(out, Print(text))?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the interface in the terminal until the player quits
+pub fn run(app: &mut App) -> io::Result<()> {
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    // Restore the terminal before a panic message is printed, or it would be lost along with
+    // the alternate screen and leave the shell in raw mode
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore();
+        hook(info);
+    }));
+    let result = event_loop(app, &mut out);
+    let _ = panic::take_hook();
+    restore()?;
+    result
+}
+
+fn restore() -> io::Result<()> {
+    execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()
+}
+
+fn event_loop(app: &mut App, out: &mut impl Write) -> io::Result<()> {
+    app.compact = terminal::size()?.1 < FULL_HEIGHT;
+    loop {
+        app.draw(out)?;
+        out.flush()?;
+        // Anything else, such as a resize, just redraws
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                if let Some(action) = Action::from_key(key) {
+                    if !app.apply(action) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        grid::Cell,
+        parser::parse_grid,
+        puzzle::Puzzle,
+        tui::{Action, App},
+    };
+
+    #[test]
+    fn test_app() {
+        let solver = Puzzle {
+            grid: Default::default(),
+            constraints: vec![],
+        }
+        .solver();
+        let grid = parse_grid(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        )
+        .unwrap();
+        let mut app = App::new(&solver, grid.clone(), &[]);
+
+        // Givens can't be changed
+        app.apply(Action::Digit(0));
+        assert_eq!(app.grid[0][0], Cell::Solved(4));
+
+        // r1c3 is 4, so a 1 there is a mistake
+        app.apply(Action::Move(0, -1));
+        app.apply(Action::Move(0, 3));
+        assert_eq!(app.cursor, (0, 2));
+        app.apply(Action::Digit(0));
+        app.apply(Action::Check);
+        assert_eq!(app.mistakes, [(0, 2)]);
+
+        app.apply(Action::Undo);
+        assert_eq!(app.grid.to_line(), grid.to_line());
+        assert!(app.mistakes.is_empty());
+        app.apply(Action::Redo);
+        assert_eq!(app.grid[0][2], Cell::Solved(0));
+        app.apply(Action::Clear);
+
+        // Marks start empty, and toggle one digit at a time
+        app.apply(Action::TogglePencil);
+        app.apply(Action::Digit(3));
+        assert_eq!(app.grid[0][2].candidates(), [3]);
+        app.apply(Action::Digit(0));
+        assert_eq!(app.grid[0][2].candidates(), [0, 3]);
+        app.apply(Action::Digit(3));
+        app.apply(Action::Digit(0));
+        assert_eq!(app.grid[0][2], Cell::Unsolved([true; 9]));
+
+        // The first hint is only shown, the second applies it
+        let before = app.grid.clone();
+        app.apply(Action::Hint);
+        assert!(app.grid.to_pencil_marks() == before.to_pencil_marks() && app.hint.is_some());
+        app.apply(Action::Hint);
+        assert!(app.grid.to_pencil_marks() != before.to_pencil_marks() && app.hint.is_none());
+
+        let mut out = vec![];
+        app.draw(&mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("r1c3"));
+        assert!(!app.apply(Action::Quit));
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use crate::{
+    basic_sudoku::{self, hidden_single_house, Pattern},
+    grid::{Cell, Grid},
+    puzzle::Constraint,
+    svg::{self, Highlight},
+};
+
+/// One deduction of a logical solve, ready to be written out
+pub struct Step {
+    pub strategy: &'static str,
+    pub explanation: String,
+    pub highlight: Highlight,
+    /// The grid once the step has been applied
+    pub grid: Grid,
+}
+impl Step {
+    pub fn new(strategy: &'static str, before: &Grid, after: &Grid) -> Step {
+        let highlight = Highlight::from_step(strategy, before, after);
+        Step {
+            strategy,
+            explanation: explain(strategy, before, after, &highlight),
+            highlight,
+            grid: after.clone(),
+        }
+    }
+}
+
+/// A strategy's name as a heading, e.g. `Hidden singles` for `hidden-singles`
+fn title(strategy: &str) -> String {
+    let words = strategy.replace('-', " ");
+    let mut chars = words.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+pub fn cell_name((r, c): (usize, usize)) -> String {
+    format!("r{}c{}", r + 1, c + 1)
+}
+fn house_name(grid: &Grid, i: usize) -> String {
+    match i {
+        0..=8 => format!("row {}", i + 1),
+        9..=17 => format!("column {}", i - 8),
+        18..=26 if grid.regions.is_some() => format!("region {}", i - 17),
+        18..=26 => format!("box {}", i - 17),
+        _ => format!("extra house {}", i - 26),
+    }
+}
+/// Joins items as `a`, `a and b` or `a, b and c`
+fn list(items: Vec<String>) -> String {
+    join(items, "and")
+}
+/// Joins items as `a`, `a or b` or `a, b or c`, or with any other last word
+fn join(items: Vec<String>, last_word: &str) -> String {
+    match items.split_last() {
+        Some((last, rest)) if !rest.is_empty() => {
+            format!("{} {} {}", rest.join(", "), last_word, last)
+        }
+        Some((last, _)) => last.clone(),
+        None => String::new(),
+    }
+}
+fn cell_list(cells: &[(usize, usize)]) -> String {
+    list(cells.iter().map(|&cell| cell_name(cell)).collect())
+}
+
+/// One tuple or pointing pattern, naming its cells and the house it was found in
+fn describe(strategy: &str, grid: &Grid, pattern: &Pattern) -> String {
+    let digits = pattern.digits.iter().map(|n| (n + 1).to_string());
+    let house = house_name(grid, pattern.house);
+    let targets = pattern
+        .eliminations
+        .iter()
+        .map(|(cell, _)| *cell)
+        .unique()
+        .collect_vec();
+    match strategy {
+        "naked-tuples" => format!(
+            "{} can only be {}, so {} {} removed from {} in {}",
+            cell_list(&pattern.cells),
+            join(digits.clone().collect(), "or"),
+            list(digits.collect()),
+            if pattern.digits.len() == 1 {
+                "is"
+            } else {
+                "are"
+            },
+            cell_list(&targets),
+            house
+        ),
+        "hidden-tuples" => format!(
+            "{} in {} can only go in {}, so their other candidates are removed",
+            list(digits.collect()),
+            house,
+            cell_list(&pattern.cells)
+        ),
+        _ => {
+            // The house the digit was removed from holds both the pattern and its targets
+            let line = (0..grid.house_count())
+                .find(|&i| {
+                    let cells = grid.house_cells(i);
+                    i != pattern.house
+                        && pattern
+                            .cells
+                            .iter()
+                            .chain(&targets)
+                            .all(|c| cells.contains(c))
+                })
+                .map(|i| house_name(grid, i))
+                .unwrap_or_default();
+            format!(
+                "{} in {} can only go in {}, all in {}, so it is removed from {} in the rest of {}",
+                list(digits.collect()),
+                house,
+                cell_list(&pattern.cells),
+                line,
+                cell_list(&targets),
+                line
+            )
+        }
+    }
+}
+
+/// Describes what a step did in plain English. Singles are explained by where the digit had
+/// to go, tuples and pointing by the cells and house they were found in, and any other
+/// strategy by the digits it placed and the candidates it removed
+pub fn explain(strategy: &str, before: &Grid, after: &Grid, highlight: &Highlight) -> String {
+    let mut sentences = Vec::new();
+    match strategy {
+        "naked-singles" => {
+            for &cell in &highlight.placements {
+                if let Cell::Solved(n) = after[cell.0][cell.1] {
+                    sentences.push(format!("{} can only be {}", cell_name(cell), n + 1));
+                }
+            }
+        }
+        "hidden-singles" => {
+            let singles = (0..9).cartesian_product(0..9).filter_map(|(r, c)| {
+                match (before[r][c], after[r][c].candidates().as_slice()) {
+                    (Cell::Unsolved(cands), &[n]) if cands.iter().filter(|t| **t).count() > 1 => {
+                        Some(((r, c), n))
+                    }
+                    _ => None,
+                }
+            });
+            for (cell, n) in singles {
+                sentences.push(match hidden_single_house(before, cell, n) {
+                    Some(i) => format!(
+                        "{} in {} can only go in {}",
+                        n + 1,
+                        house_name(before, i),
+                        cell_name(cell)
+                    ),
+                    None => format!("{} must be {}", cell_name(cell), n + 1),
+                });
+            }
+        }
+        "naked-tuples" | "hidden-tuples" | "pointing-tuples" => {
+            for pattern in basic_sudoku::patterns(strategy, before) {
+                sentences.push(describe(strategy, before, &pattern));
+            }
+        }
+        _ => {
+            let placements = highlight.placements.iter().filter_map(|&cell| {
+                let Cell::Solved(n) = after[cell.0][cell.1] else {
+                    return None;
+                };
+                Some(format!("{} in {}", n + 1, cell_name(cell)))
+            });
+            let placements = placements.collect_vec();
+            if !placements.is_empty() {
+                sentences.push(format!("places {}", list(placements)));
+            }
+            let eliminations = highlight
+                .eliminations
+                .iter()
+                .into_group_map_by(|(_, digit)| *digit)
+                .into_iter()
+                .sorted()
+                .map(|(digit, cells)| {
+                    let cells = cells.iter().map(|(cell, _)| cell_name(*cell)).collect();
+                    format!("{} from {}", digit + 1, list(cells))
+                })
+                .collect_vec();
+            if !eliminations.is_empty() {
+                sentences.push(format!("removes {}", eliminations.join("; ")));
+            }
+        }
+    }
+    format!("{}: {}", title(strategy), sentences.join("; "))
+}
+
+/// Writes the steps as Markdown, with the pencil marks after each step in a code block
+pub fn to_markdown(start: &Grid, steps: &[Step]) -> String {
+    let mut out = String::from("# Walkthrough\n\n## Start\n\n");
+    let _ = writeln!(out, "```\n{}```\n", start.to_pencil_marks());
+    for (i, step) in steps.iter().enumerate() {
+        let _ = writeln!(out, "## Step {}: {}\n", i + 1, title(step.strategy));
+        let _ = writeln!(out, "{}\n", step.explanation);
+        let _ = writeln!(out, "```\n{}```\n", step.grid.to_pencil_marks());
+    }
+    out += &summary(steps.last().map_or(start, |step| &step.grid));
+    out
+}
+
+/// Writes the steps as a standalone HTML page, drawing the grid after each step with the
+/// cells it changed highlighted
+pub fn to_html(start: &Grid, steps: &[Step], constraints: &[Constraint]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Walkthrough</title>\n\
+         <style>body { font-family: sans-serif; } svg { width: 400px; height: 400px; }</style>\n\
+         </head>\n<body>\n<h1>Walkthrough</h1>\n<h2>Start</h2>\n",
+    );
+    out += &svg::render(start, start, constraints, None);
+    for (i, step) in steps.iter().enumerate() {
+        let _ = writeln!(out, "<h2>Step {}: {}</h2>", i + 1, title(step.strategy));
+        let _ = writeln!(out, "<p>{}</p>", step.explanation);
+        out += &svg::render(&step.grid, start, constraints, Some(&step.highlight));
+    }
+    let final_grid = steps.last().map_or(start, |step| &step.grid);
+    let _ = writeln!(
+        out,
+        "<p>{}</p>\n</body>\n</html>",
+        summary(final_grid).trim()
+    );
+    out
+}
+
+fn summary(grid: &Grid) -> String {
+    if grid.solved() && !grid.broken() {
+        "The puzzle is solved.\n".to_string()
+    } else {
+        "No further logical steps were found.\n".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        basic_sudoku::{
+            basic_elimination, hidden_singles, naked_singles, naked_tuples, pointing_tuples,
+        },
+        grid::Cell,
+        parser::parse_grid,
+        walkthrough::{to_html, to_markdown, Step},
+    };
+
+    #[test]
+    fn test_walkthrough() {
+        // Each 9 rules out a row or column of box 1, leaving only r1c1
+        let mut givens = ['.'; 81];
+        for i in [12, 24, 28, 56] {
+            givens[i] = '9';
+        }
+        let start = parse_grid(&givens.iter().collect::<String>()).unwrap();
+
+        let mut after = start.clone();
+        basic_elimination(&mut after);
+        let elimination = Step::new("basic-elimination", &start, &after);
+        assert!(elimination
+            .explanation
+            .starts_with("Basic elimination: removes 9 from r1c2, r1c3, "));
+
+        let before = after.clone();
+        hidden_singles(&mut after);
+        let hidden = Step::new("hidden-singles", &before, &after);
+        assert!(hidden
+            .explanation
+            .starts_with("Hidden singles: 9 in row 1 can only go in r1c1"));
+
+        let before = after.clone();
+        naked_singles(&mut after);
+        assert_eq!(after[0][0], Cell::Solved(8));
+        let naked = Step::new("naked-singles", &before, &after);
+        assert!(naked
+            .explanation
+            .starts_with("Naked singles: r1c1 can only be 9"));
+
+        // A naked pair in row 1, and 9 confined to row 2 of box 3
+        let mut grid = parse_grid(&".".repeat(81)).unwrap();
+        let pair = [true, true, false, false, false, false, false, false, false];
+        grid[0][0] = Cell::Unsolved(pair);
+        grid[0][4] = Cell::Unsolved(pair);
+        let mut after = grid.clone();
+        naked_tuples(&mut after);
+        let tuple = Step::new("naked-tuples", &grid, &after);
+        assert!(tuple.explanation.starts_with(
+            "Naked tuples: r1c1 and r1c5 can only be 1 or 2, so 1 and 2 are removed from r1c2, "
+        ));
+        assert!(tuple.explanation.contains(" in row 1"));
+        for (r, c) in (0..3).flat_map(|r| (6..9).map(move |c| (r, c))) {
+            if r != 1 {
+                grid[r][c].remove_candidate(8);
+            }
+        }
+        let mut after = grid.clone();
+        pointing_tuples(&mut after);
+        let pointing = Step::new("pointing-tuples", &grid, &after);
+        assert_eq!(
+            pointing.explanation,
+            "Pointing tuples: 9 in box 3 can only go in r2c7, r2c8 and r2c9, all in row 2, so it \
+             is removed from r2c1, r2c2, r2c3, r2c4, r2c5 and r2c6 in the rest of row 2"
+        );
+
+        let steps = [elimination, hidden, naked];
+        let markdown = to_markdown(&start, &steps);
+        assert!(markdown.contains("## Step 3: Naked singles\n\nNaked singles: r1c1 can only be 9"));
+        assert!(markdown.ends_with("No further logical steps were found.\n"));
+        let html = to_html(&start, &steps, &[]);
+        assert_eq!(html.matches("<svg").count(), 4);
+    }
+}